@@ -0,0 +1,102 @@
+// Baselines for the emulator core, run headless on small synthetic programs that loop
+// forever, so each iteration does the same work.
+//
+// $ cargo bench
+
+use std::hint::black_box;
+
+use chip8_emu::emu::Emu;
+use criterion::{Criterion, criterion_group, criterion_main};
+
+// A program from its opcodes, big endian like in RAM
+fn program(ops: &[u16]) -> Vec<u8> {
+    ops.iter().flat_map(|op| op.to_be_bytes()).collect()
+}
+
+fn emu_running(rom: &[u8]) -> Emu {
+    let mut emu = Emu::new_with_seed(0);
+    emu.load(rom).unwrap();
+
+    emu
+}
+
+// Runs one pass of a loop that's `len` instructions long
+fn bench_loop(c: &mut Criterion, name: &str, mut emu: Emu, len: usize) {
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            for _ in 0..len {
+                emu.tick().unwrap();
+            }
+
+            black_box(&emu);
+        })
+    });
+}
+
+// Arithmetic, logic, skips and memory access, with no drawing
+fn tick(c: &mut Criterion) {
+    let rom = program(&[
+        0x6001, // 200: LD V0, 0x01
+        0x7103, // 202: ADD V1, 0x03
+        0x8014, // 204: ADD V0, V1
+        0x8122, // 206: AND V1, V2
+        0x8306, // 208: SHR V3, V0
+        0x3000, // 20A: SE V0, 0x00
+        0xC2FF, // 20C: RND V2, 0xFF
+        0xA300, // 20E: LD I, 0x300
+        0xF233, // 210: LD B, V2
+        0xF265, // 212: LD V2, [I]
+        0x1202, // 214: JP 0x202
+    ]);
+
+    let mut emu = emu_running(&rom);
+    // Past the one time setup
+    emu.tick().unwrap();
+
+    bench_loop(c, "tick", emu, 10);
+}
+
+// A full 8x15 sprite drawn across the screen, moving each time
+fn draw(c: &mut Criterion) {
+    let mut rom = program(&[
+        0xA20A, // 200: LD I, 0x20A
+        0xD01F, // 202: DRW V0, V1, 15
+        0x7008, // 204: ADD V0, 0x08
+        0x7105, // 206: ADD V1, 0x05
+        0x1202, // 208: JP 0x202
+    ]);
+    rom.extend([0xFF; 15]);
+
+    let mut emu = emu_running(&rom);
+    emu.tick().unwrap();
+
+    bench_loop(c, "draw", emu, 4);
+}
+
+fn clear(c: &mut Criterion) {
+    let rom = program(&[
+        0x00E0, // 200: CLS
+        0x1200, // 202: JP 0x200
+    ]);
+
+    bench_loop(c, "clear", emu_running(&rom), 2);
+}
+
+// Every direction, in hi-res where the whole 128x64 display moves
+fn scroll(c: &mut Criterion) {
+    let rom = program(&[
+        0x00FF, // 200: HIGH
+        0x00C4, // 202: SCD 4
+        0x00FB, // 204: SCR
+        0x00FC, // 206: SCL
+        0x1202, // 208: JP 0x202
+    ]);
+
+    let mut emu = emu_running(&rom);
+    emu.tick().unwrap();
+
+    bench_loop(c, "scroll", emu, 4);
+}
+
+criterion_group!(benches, tick, draw, clear, scroll);
+criterion_main!(benches);
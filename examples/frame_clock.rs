@@ -0,0 +1,78 @@
+// Drives the emulator from a plain std thread timer instead of the SDL game loop, the same way a
+// web or GUI host would from requestAnimationFrame or its own timer callback.
+//
+// $ cargo run --example frame_clock path/to/rom
+
+use std::{
+    env, fs,
+    process::ExitCode,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chip8_emu::emu::Emu;
+
+const FRAME_TIME: Duration = Duration::from_micros(1_000_000 / 60);
+const RUN_TIME: Duration = Duration::from_secs(5);
+
+fn main() -> ExitCode {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage: cargo run --example frame_clock path/to/game");
+        return ExitCode::FAILURE;
+    }
+
+    let data = match fs::read(&args[1]) {
+        Ok(data) => data,
+        Err(_) => {
+            eprintln!("Unable to load emulator file!");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut emu = Emu::new();
+
+    if let Err(err) = emu.load(&data) {
+        eprintln!("Unable to load emulator file! {err}");
+        return ExitCode::FAILURE;
+    }
+
+    // The "host" clock: fires once per frame, completely independent of the emulator
+    let (clock_tx, clock_rx) = mpsc::channel();
+    thread::spawn(move || {
+        while clock_tx.send(()).is_ok() {
+            thread::sleep(FRAME_TIME);
+        }
+    });
+
+    let start = Instant::now();
+
+    for () in clock_rx {
+        if start.elapsed() >= RUN_TIME {
+            break;
+        }
+
+        if let Err(err) = emu.run_frame() {
+            eprintln!("Emulation stopped: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let (width, _) = emu.display_size();
+    print_frame(&emu.get_frame_rgba(), width);
+
+    ExitCode::SUCCESS
+}
+
+fn print_frame(rgba: &[u8], width: usize) {
+    for row in rgba.chunks(width * 4) {
+        let line: String = row
+            .chunks(4)
+            .map(|pixel| if pixel[0] > 0 { '#' } else { '.' })
+            .collect();
+
+        println!("{line}");
+    }
+}
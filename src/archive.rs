@@ -0,0 +1,165 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, IsTerminal, Read, Write},
+    path::Path,
+};
+
+use zip::ZipArchive;
+
+const ROM_EXTENSIONS: [&str; 2] = ["ch8", "rom"];
+
+pub fn is_zip(path: &Path) -> bool {
+    has_extension(path, "zip")
+}
+
+// Reads a ROM out of a zip, e.g. a collection of games. `entry` picks one by its name in the
+// archive, with or without the folders it's in. Without one, the only ROM is read, or if there
+// are several and `ask` is set, the player picks one from a list on stdin.
+pub fn read_rom(path: &Path, entry: Option<&str>, ask: bool) -> io::Result<Vec<u8>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+
+    let roms: Vec<String> = archive
+        .file_names()
+        .filter_map(Result::ok)
+        .filter(|name| {
+            ROM_EXTENSIONS
+                .iter()
+                .any(|extension| has_extension(Path::new(name.as_ref()), extension))
+        })
+        .map(|name| name.into_owned())
+        .collect();
+
+    let name = match entry {
+        Some(entry) => roms
+            .iter()
+            .find(|name| {
+                name.as_str() == entry
+                    || Path::new(name)
+                        .file_name()
+                        .is_some_and(|file| file == entry)
+            })
+            .ok_or_else(|| not_found(format!("no game called {entry} in {}", path.display())))?,
+        None => match roms.as_slice() {
+            [] => return Err(not_found(format!("no games in {}", path.display()))),
+            [name] => name,
+            _ if ask && io::stdin().is_terminal() => choose(&roms)?,
+            _ => {
+                return Err(io::Error::other(format!(
+                    "{} has several games, pick one with --entry: {}",
+                    path.display(),
+                    roms.join(", ")
+                )));
+            }
+        },
+    };
+
+    let mut rom = Vec::new();
+    archive.by_name(name)?.read_to_end(&mut rom)?;
+
+    Ok(rom)
+}
+
+// Asks until it gets a number from the list
+fn choose(roms: &[String]) -> io::Result<&String> {
+    for (n, name) in roms.iter().enumerate() {
+        println!("{:>3}  {name}", n + 1);
+    }
+
+    let mut lines = io::stdin().lock().lines();
+
+    loop {
+        print!("Which game? ");
+        io::stdout().flush()?;
+
+        let line = lines
+            .next()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))??;
+
+        match line.trim().parse::<usize>() {
+            Ok(n) if (1..=roms.len()).contains(&n) => return Ok(&roms[n - 1]),
+            _ => println!("Pick a number from 1 to {}", roms.len()),
+        }
+    }
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|path_extension| path_extension.eq_ignore_ascii_case(extension))
+}
+
+fn not_found(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process};
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+
+    // A zip in the temp directory with the given files, deleted when dropped
+    struct TempZip(PathBuf);
+
+    impl TempZip {
+        fn new(name: &str, files: &[(&str, &[u8])]) -> Self {
+            let path = env::temp_dir().join(format!("chip8_emu-{name}-{}.zip", process::id()));
+            let mut zip = ZipWriter::new(File::create(&path).unwrap());
+
+            for (file_name, contents) in files {
+                zip.start_file(*file_name, SimpleFileOptions::default())
+                    .unwrap();
+                zip.write_all(contents).unwrap();
+            }
+
+            zip.finish().unwrap();
+
+            Self(path)
+        }
+    }
+
+    impl Drop for TempZip {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn reads_the_only_rom() {
+        let zip = TempZip::new("one", &[("README.txt", b"hi"), ("games/pong.ch8", &[1, 2])]);
+
+        assert!(is_zip(&zip.0));
+        assert_eq!(read_rom(&zip.0, None, false).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn picks_roms_by_entry() {
+        let zip = TempZip::new(
+            "several",
+            &[("games/pong.ch8", &[1, 2]), ("games/tetris.CH8", &[3])],
+        );
+
+        // By the full name or just the file's
+        assert_eq!(
+            read_rom(&zip.0, Some("games/pong.ch8"), false).unwrap(),
+            [1, 2]
+        );
+        assert_eq!(read_rom(&zip.0, Some("tetris.CH8"), false).unwrap(), [3]);
+
+        let err = read_rom(&zip.0, Some("brix.ch8"), false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        // Without an entry, there's no telling which
+        let err = read_rom(&zip.0, None, false).unwrap_err();
+        assert!(err.to_string().contains("pick one with --entry"), "{err}");
+    }
+
+    #[test]
+    fn fails_without_roms() {
+        let zip = TempZip::new("none", &[("README.txt", b"hi")]);
+
+        let err = read_rom(&zip.0, None, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
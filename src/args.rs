@@ -0,0 +1,393 @@
+use chip8_emu::{
+    constants::{SCALE, TICKS_PER_FRAME, TIMER_FREQ},
+    quirks::Quirks,
+};
+
+use crate::{
+    audio::{self, Waveform},
+    demos, net,
+    palette::{Palette, parse_hex_color},
+};
+
+const DEFAULT_KEYMAP_PATH: &str = "keymap.toml";
+
+pub const USAGE: &str = "\
+Usage: cargo run -- [OPTIONS] path/to/game
+       cargo run -- [OPTIONS] --demo NAME
+
+The game is read from stdin if its path is -, and downloaded if it's a URL (which needs
+the net feature). Without one, the window waits for a game to be dropped onto it.
+
+Options:
+  --demo NAME           run a built in demo instead of a game
+  --list-demos          list the built in demos and exit
+  --entry NAME          game to run from a zip with several in it
+  --base64 DATA         run a game given as base64 text, or read from stdin if DATA is -
+  --disasm              print a disassembly of the ROM and exit
+  --listing FILE        write a disassembly with labels for jumps and calls to FILE
+  --check               look for problems in the ROM's code without running it
+  --debug               step through the ROM from a debugger prompt
+  --break-on-unknown    drop into the debugger at an unknown opcode, rather than stopping
+  --terminal            draw in the terminal instead of opening a window
+  --trace FILE          log every executed instruction to FILE
+  --record FILE         log keypad input and the random seed to FILE
+  --replay FILE         play back keypad input logged with --record
+  --profile             print how often each kind of instruction ran, on exit
+  --hotspots N          print the N most executed addresses, on exit
+  --warn-smc            warn about writes to code that has already run
+  --seed N              seed the random number generator, for reproducible runs
+  --scale N             window scale factor (default 15)
+  --speed N             instructions per frame (default 10)
+  --idle-sleep          skip the rest of a frame once the game is just waiting
+  --no-vsync            don't wait for the display between frames
+  --fps N               frames drawn per second with --no-vsync (default 60)
+  --theme NAME          classic (default), amber, green-phosphor, gameboy or inverted
+  --fg-color #RRGGBB    color of lit pixels, overriding the theme
+  --bg-color #RRGGBB    color of unlit pixels, overriding the theme
+  --fade N              frames pixels take to fade out, to hide flicker (default 0, off)
+  --waveform NAME       beep shape: square (default), sine, triangle or sawtooth
+  --beep-freq HZ        beep pitch (default 440)
+  --quirks PROFILE      cowgod (default), chip8, schip, xochip (with 64 KB of RAM),
+                        or auto to look the game up in roms/database.txt
+  --quirk NAME=on|off   turn one quirk on or off on top of the profile, e.g. wrap_x=off
+  --start-paused        wait before the first instruction, to step through from there
+  --no-pause-unfocused  keep running while the window is in the background
+  --keymap FILE         keyboard mapping config (default keymap.toml, if present)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Run,
+    Disasm,
+    Check,
+    Debug,
+    Terminal,
+    ListDemos,
+}
+
+pub struct Args {
+    // Named after the demo when running one, the file in a URL, or base64.ch8 for --base64, so
+    // captures and save states still get a name.
+    // Empty when the window is opened without a game, to have one dropped onto it.
+    pub rom_path: String,
+    pub demo: Option<&'static [u8]>,
+    pub entry: Option<String>,
+    // Still encoded, or - to read it from stdin
+    pub base64: Option<String>,
+    // When the game is given as a URL rather than a path
+    pub url: Option<String>,
+    pub mode: Mode,
+    // Where --listing writes to, for Mode::Disasm
+    pub listing_path: Option<String>,
+    pub break_on_unknown: bool,
+    pub trace_path: Option<String>,
+    pub record_path: Option<String>,
+    pub replay_path: Option<String>,
+    pub profile: bool,
+    // 0 when not counting
+    pub hotspots: usize,
+    pub warn_smc: bool,
+    pub seed: Option<u64>,
+    pub scale: u32,
+    pub speed: usize,
+    pub idle_sleep: bool,
+    pub vsync: bool,
+    pub fps: u32,
+    pub palette: Palette,
+    pub fade: u32,
+    pub waveform: Waveform,
+    pub beep_freq: f32,
+    // None for --quirks auto, which picks them once the game is loaded
+    pub quirks: Option<Quirks>,
+    // From --quirk, to apply on top of the profile in the order given
+    pub quirk_flags: Vec<(&'static str, bool)>,
+    pub keymap_path: String,
+    pub start_paused: bool,
+    pub pause_unfocused: bool,
+}
+
+// Parses everything after the program name. Errors are meant to be shown along with USAGE.
+pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut rom_path = None;
+    let mut demo = None;
+    let mut mode = Mode::Run;
+    let mut listing_path = None;
+    let mut break_on_unknown = false;
+    let mut entry = None;
+    let mut base64 = None;
+    let mut url = None;
+    let mut trace_path = None;
+    let mut record_path = None;
+    let mut replay_path = None;
+    let mut profile = false;
+    let mut hotspots = 0;
+    let mut warn_smc = false;
+    let mut seed = None;
+    let mut scale = SCALE;
+    let mut speed = TICKS_PER_FRAME;
+    let mut idle_sleep = false;
+    let mut vsync = true;
+    let mut fps = TIMER_FREQ;
+    let mut palette = Palette::default();
+    let mut fg_color = None;
+    let mut bg_color = None;
+    let mut fade = 0;
+    let mut waveform = Waveform::default();
+    let mut beep_freq = audio::BEEP_FREQ;
+    let mut quirks = Some(Quirks::default());
+    let mut quirk_flags = Vec::new();
+    let mut keymap_path = DEFAULT_KEYMAP_PATH.to_string();
+    let mut start_paused = false;
+    let mut pause_unfocused = true;
+
+    while let Some(arg) = args.next() {
+        // Flags that take a value
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+
+        match arg.as_str() {
+            "--disasm" => mode = Mode::Disasm,
+            "--listing" => {
+                mode = Mode::Disasm;
+                listing_path = Some(value()?);
+            }
+            "--debug" => mode = Mode::Debug,
+            "--break-on-unknown" => break_on_unknown = true,
+            "--terminal" => mode = Mode::Terminal,
+            "--check" => mode = Mode::Check,
+            "--list-demos" => mode = Mode::ListDemos,
+            "--demo" => {
+                let name = value()?;
+
+                if rom_path.is_some() && demo.is_none() {
+                    return Err("Either give a game or a --demo, not both".to_string());
+                }
+
+                demo = Some(demos::find(&name).ok_or(format!(
+                    "Unknown demo {name}, see --list-demos for the choices"
+                ))?);
+                rom_path = Some(format!("{name}.ch8"));
+            }
+            "--entry" => entry = Some(value()?),
+            "--base64" => {
+                if rom_path.is_some() {
+                    return Err("Either give a game or --base64, not both".to_string());
+                }
+
+                base64 = Some(value()?);
+                rom_path = Some("base64.ch8".to_string());
+            }
+            "--trace" => trace_path = Some(value()?),
+            "--record" => record_path = Some(value()?),
+            "--replay" => replay_path = Some(value()?),
+            "--profile" => profile = true,
+            "--hotspots" => hotspots = parse_positive(&arg, &value()?)?,
+            "--warn-smc" => warn_smc = true,
+            "--seed" => {
+                let value = value()?;
+                seed = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("{arg} needs a number, got {value}"))?,
+                );
+            }
+            "--scale" => scale = parse_positive(&arg, &value()?)?,
+            "--speed" => speed = parse_positive(&arg, &value()?)?,
+            "--idle-sleep" => idle_sleep = true,
+            "--no-vsync" => vsync = false,
+            "--fps" => fps = parse_positive(&arg, &value()?)?,
+            "--theme" => {
+                let name = value()?;
+                palette = Palette::theme(&name).ok_or_else(|| {
+                    let names: Vec<_> = Palette::theme_names().collect();
+                    format!("Unknown theme {name}, expected one of {}", names.join(", "))
+                })?;
+            }
+            "--fg-color" => fg_color = Some(parse_color(&arg, &value()?)?),
+            "--bg-color" => bg_color = Some(parse_color(&arg, &value()?)?),
+            "--fade" => {
+                let value = value()?;
+                fade = value
+                    .parse()
+                    .map_err(|_| format!("{arg} needs a number, got {value}"))?;
+            }
+            "--waveform" => {
+                let name = value()?;
+                waveform = Waveform::from_name(&name).ok_or_else(|| {
+                    let names: Vec<_> = Waveform::names().collect();
+                    format!(
+                        "Unknown waveform {name}, expected one of {}",
+                        names.join(", ")
+                    )
+                })?;
+            }
+            "--beep-freq" => beep_freq = parse_positive(&arg, &value()?)?,
+            "--quirks" => {
+                let profile = value()?;
+                quirks = match profile.as_str() {
+                    "auto" => None,
+                    _ => Some(
+                        Quirks::preset(&profile)
+                            .ok_or(format!("Unknown quirks profile {profile}"))?,
+                    ),
+                };
+            }
+            "--quirk" => quirk_flags.push(parse_quirk(&value()?)?),
+            "--keymap" => keymap_path = value()?,
+            "--start-paused" => start_paused = true,
+            "--no-pause-unfocused" => pause_unfocused = false,
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {flag}")),
+            _ if rom_path.is_some() => return Err(format!("Unexpected argument {arg}")),
+            _ if net::is_url(&arg) => {
+                rom_path = Some(net::file_name(&arg));
+                url = Some(arg);
+            }
+            _ => rom_path = Some(arg),
+        }
+    }
+
+    // The terminal has no way to record, replay, pause or drop into the debugger
+    if mode == Mode::Terminal {
+        let windowed_only = [
+            ("--record", record_path.is_some()),
+            ("--replay", replay_path.is_some()),
+            ("--start-paused", start_paused),
+            ("--break-on-unknown", break_on_unknown),
+        ];
+
+        if let Some((flag, _)) = windowed_only.iter().find(|(_, given)| *given) {
+            return Err(format!("{flag} doesn't work with --terminal"));
+        }
+    }
+
+    // Explicit colors win over the theme, whatever order they were given in
+    palette.fg = fg_color.unwrap_or(palette.fg);
+    palette.bg = bg_color.unwrap_or(palette.bg);
+
+    Ok(Args {
+        rom_path: match rom_path {
+            Some(rom_path) => rom_path,
+            // Nothing gets loaded when just listing demos, and the window waits for a game to be
+            // dropped onto it
+            None if matches!(mode, Mode::ListDemos | Mode::Run) => String::new(),
+            None => return Err("Missing path to game".to_string()),
+        },
+        demo,
+        entry,
+        base64,
+        url,
+        mode,
+        listing_path,
+        break_on_unknown,
+        trace_path,
+        record_path,
+        replay_path,
+        profile,
+        hotspots,
+        warn_smc,
+        seed,
+        scale,
+        speed,
+        idle_sleep,
+        vsync,
+        fps,
+        palette,
+        fade,
+        waveform,
+        beep_freq,
+        quirks,
+        quirk_flags,
+        keymap_path,
+        start_paused,
+        pause_unfocused,
+    })
+}
+
+// A NAME=on|off for --quirk
+fn parse_quirk(value: &str) -> Result<(&'static str, bool), String> {
+    let (name, setting) = value
+        .split_once('=')
+        .ok_or(format!("--quirk needs NAME=on or NAME=off, got {value}"))?;
+    let name = Quirks::NAMES
+        .into_iter()
+        .find(|&known| known == name)
+        .ok_or_else(|| {
+            format!(
+                "Unknown quirk {name}, expected one of {}",
+                Quirks::NAMES.join(", ")
+            )
+        })?;
+
+    match setting {
+        "on" => Ok((name, true)),
+        "off" => Ok((name, false)),
+        _ => Err(format!("--quirk {name} needs on or off, got {setting}")),
+    }
+}
+
+fn parse_positive<T: std::str::FromStr + Default + PartialOrd>(
+    flag: &str,
+    value: &str,
+) -> Result<T, String> {
+    match value.parse() {
+        Ok(n) if n > T::default() => Ok(n),
+        _ => Err(format!("{flag} needs a positive number, got {value}")),
+    }
+}
+
+fn parse_color(flag: &str, value: &str) -> Result<sdl2::pixels::Color, String> {
+    parse_hex_color(value).ok_or(format!("{flag} needs a color like #33FF66, got {value}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &str) -> Result<Args, String> {
+        parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn rejects_windowed_options_in_the_terminal() {
+        for flag in [
+            "--record in.log",
+            "--replay in.log",
+            "--start-paused",
+            "--break-on-unknown",
+        ] {
+            let err = parse_args(&format!("--terminal {flag} game.ch8"))
+                .err()
+                .unwrap();
+
+            assert!(err.ends_with("doesn't work with --terminal"), "{err}");
+        }
+
+        assert!(parse_args("--terminal --speed 20 game.ch8").is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_themes() {
+        assert_eq!(
+            parse_args("--theme sepia game.ch8").err(),
+            Some(
+                "Unknown theme sepia, expected one of classic, amber, green-phosphor, gameboy, \
+                 inverted"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn parses_quirk_flags() {
+        let args =
+            parse_args("--quirks schip --quirk wrap_y=on --quirk wrap_pc=off game.ch8").unwrap();
+
+        assert_eq!(args.quirks, Some(Quirks::schip()));
+        assert_eq!(args.quirk_flags, [("wrap_y", true), ("wrap_pc", false)]);
+    }
+
+    #[test]
+    fn rejects_bad_quirk_flags() {
+        assert!(parse_args("--quirk wrap_z=on game.ch8").is_err());
+        assert!(parse_args("--quirk wrap_x=yes game.ch8").is_err());
+        assert!(parse_args("--quirk wrap_x game.ch8").is_err());
+    }
+}
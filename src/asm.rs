@@ -0,0 +1,380 @@
+// A minimal assembler, for writing test ROMs inline. The syntax is what `disasm` prints, so a
+// disassembly assembles back into the same ROM, plus labels:
+//
+//     loop:   ADD V0, 1        ; comments run to the end of the line
+//             JP loop
+//     sprite: DB 0xF0, 0x90, 0xF0
+//
+// Numbers are decimal, or hex/binary with a 0x/0b prefix. Labels can be used before they're
+// defined.
+
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+use core::{error, fmt};
+
+use crate::constants::START_ADDR;
+
+const MNEMONICS: [&str; 30] = [
+    "NOP", "CLS", "RET", "SCD", "SCR", "SCL", "LOW", "HIGH", "JP", "CALL", "SE", "SNE", "LD",
+    "ADD", "OR", "AND", "XOR", "SUB", "SHR", "SUBN", "SHL", "RND", "DRW", "SKP", "SKNP", "PLANE",
+    "AUDIO", "PITCH", "DB", "DW",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    // Counting from 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for AsmError {}
+
+enum Operand<'a> {
+    Reg(u8),
+    I,
+    // [I], the memory I points at
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    Hf,
+    B,
+    R,
+    // LONG with the address for F000, if it's given here rather than as the next word
+    Long(Option<&'a str>),
+    // A number or label
+    Value(&'a str),
+}
+
+impl<'a> Operand<'a> {
+    fn parse(operand: &'a str) -> Self {
+        if let Some(addr) = long_address(operand) {
+            return Operand::Long(addr);
+        }
+
+        match operand.to_ascii_uppercase().as_str() {
+            "I" => Operand::I,
+            "[I]" => Operand::IndirectI,
+            "DT" => Operand::Dt,
+            "ST" => Operand::St,
+            "K" => Operand::K,
+            "F" => Operand::F,
+            "HF" => Operand::Hf,
+            "B" => Operand::B,
+            "R" => Operand::R,
+            // VA-VF, or V10-V15 like `disasm` prints them
+            reg if reg.starts_with('V') => {
+                let digits = &reg[1..];
+
+                let x = match digits.len() {
+                    1 => u8::from_str_radix(digits, 16).ok(),
+                    2 => digits.parse().ok().filter(|&x| x < 16),
+                    _ => None,
+                };
+
+                x.map_or(Operand::Value(operand), Operand::Reg)
+            }
+            _ => Operand::Value(operand),
+        }
+    }
+}
+
+// An instruction or data directive, with any label on its line already taken off
+struct Statement<'a> {
+    line: usize,
+    mnemonic: String,
+    operands: Vec<&'a str>,
+}
+
+impl Statement<'_> {
+    fn size(&self) -> usize {
+        match self.mnemonic.as_str() {
+            "DB" => self.operands.len(),
+            "DW" => self.operands.len() * 2,
+            // F000 and its address, when it's given on the same line
+            "LD" if self
+                .operands
+                .get(1)
+                .and_then(|&operand| long_address(operand))
+                .flatten()
+                .is_some() =>
+            {
+                4
+            }
+            _ => 2,
+        }
+    }
+
+    fn encode(&self, labels: &BTreeMap<&str, u32>, rom: &mut Vec<u8>) -> Result<(), String> {
+        let value = |text: &str, max: u32| resolve(text, labels, max).map(|value| value as u16);
+
+        match self.mnemonic.as_str() {
+            "DB" => {
+                for operand in &self.operands {
+                    rom.push(value(operand, 0xFF)? as u8);
+                }
+
+                return Ok(());
+            }
+            "DW" => {
+                for operand in &self.operands {
+                    rom.extend(value(operand, 0xFFFF)?.to_be_bytes());
+                }
+
+                return Ok(());
+            }
+            _ => (),
+        }
+
+        let operands: Vec<_> = self
+            .operands
+            .iter()
+            .map(|operand| Operand::parse(operand))
+            .collect();
+
+        // Registers in their places in the opcode
+        let x = |reg: &u8| (*reg as u16) << 8;
+        let y = |reg: &u8| (*reg as u16) << 4;
+
+        use Operand::*;
+
+        let op = match (self.mnemonic.as_str(), operands.as_slice()) {
+            ("NOP", []) => 0x0000,
+            ("CLS", []) => 0x00E0,
+            ("RET", []) => 0x00EE,
+            ("SCD", [Value(n)]) => 0x00C0 | value(n, 0xF)?,
+            ("SCR", []) => 0x00FB,
+            ("SCL", []) => 0x00FC,
+            ("LOW", []) => 0x00FE,
+            ("HIGH", []) => 0x00FF,
+            ("JP", [Value(nnn)]) => 0x1000 | value(nnn, 0xFFF)?,
+            ("CALL", [Value(nnn)]) => 0x2000 | value(nnn, 0xFFF)?,
+            ("SE", [Reg(vx), Value(nn)]) => 0x3000 | x(vx) | value(nn, 0xFF)?,
+            ("SNE", [Reg(vx), Value(nn)]) => 0x4000 | x(vx) | value(nn, 0xFF)?,
+            ("SE", [Reg(vx), Reg(vy)]) => 0x5000 | x(vx) | y(vy),
+            ("LD", [Reg(vx), Value(nn)]) => 0x6000 | x(vx) | value(nn, 0xFF)?,
+            ("ADD", [Reg(vx), Value(nn)]) => 0x7000 | x(vx) | value(nn, 0xFF)?,
+            ("LD", [Reg(vx), Reg(vy)]) => 0x8000 | x(vx) | y(vy),
+            ("OR", [Reg(vx), Reg(vy)]) => 0x8001 | x(vx) | y(vy),
+            ("AND", [Reg(vx), Reg(vy)]) => 0x8002 | x(vx) | y(vy),
+            ("XOR", [Reg(vx), Reg(vy)]) => 0x8003 | x(vx) | y(vy),
+            ("ADD", [Reg(vx), Reg(vy)]) => 0x8004 | x(vx) | y(vy),
+            ("SUB", [Reg(vx), Reg(vy)]) => 0x8005 | x(vx) | y(vy),
+            ("SHR", [Reg(vx), Reg(vy)]) => 0x8006 | x(vx) | y(vy),
+            ("SUBN", [Reg(vx), Reg(vy)]) => 0x8007 | x(vx) | y(vy),
+            ("SHL", [Reg(vx), Reg(vy)]) => 0x800E | x(vx) | y(vy),
+            ("SNE", [Reg(vx), Reg(vy)]) => 0x9000 | x(vx) | y(vy),
+            ("LD", [I, Value(nnn)]) => 0xA000 | value(nnn, 0xFFF)?,
+            ("JP", [Reg(0), Value(nnn)]) => 0xB000 | value(nnn, 0xFFF)?,
+            ("RND", [Reg(vx), Value(nn)]) => 0xC000 | x(vx) | value(nn, 0xFF)?,
+            ("DRW", [Reg(vx), Reg(vy), Value(n)]) => 0xD000 | x(vx) | y(vy) | value(n, 0xF)?,
+            ("SKP", [Reg(vx)]) => 0xE09E | x(vx),
+            ("SKNP", [Reg(vx)]) => 0xE0A1 | x(vx),
+            ("LD", [I, Long(addr)]) => {
+                rom.extend(0xF000u16.to_be_bytes());
+
+                // Otherwise the address is the next statement, as `disasm` lays it out
+                if let Some(addr) = addr {
+                    rom.extend(value(addr, 0xFFFF)?.to_be_bytes());
+                }
+
+                return Ok(());
+            }
+            ("PLANE", [Value(n)]) => 0xF001 | value(n, 0xF)? << 8,
+            ("AUDIO", []) => 0xF002,
+            ("LD", [Reg(vx), Dt]) => 0xF007 | x(vx),
+            ("LD", [Reg(vx), K]) => 0xF00A | x(vx),
+            ("LD", [Dt, Reg(vx)]) => 0xF015 | x(vx),
+            ("LD", [St, Reg(vx)]) => 0xF018 | x(vx),
+            ("ADD", [I, Reg(vx)]) => 0xF01E | x(vx),
+            ("LD", [F, Reg(vx)]) => 0xF029 | x(vx),
+            ("LD", [Hf, Reg(vx)]) => 0xF030 | x(vx),
+            ("LD", [B, Reg(vx)]) => 0xF033 | x(vx),
+            ("PITCH", [Reg(vx)]) => 0xF03A | x(vx),
+            ("LD", [IndirectI, Reg(vx)]) => 0xF055 | x(vx),
+            ("LD", [Reg(vx), IndirectI]) => 0xF065 | x(vx),
+            ("LD", [R, Reg(vx)]) => 0xF075 | x(vx),
+            ("LD", [Reg(vx), R]) => 0xF085 | x(vx),
+            (mnemonic, _) if MNEMONICS.contains(&mnemonic) => {
+                return Err(format!("invalid operands for {mnemonic}"));
+            }
+            (mnemonic, _) => return Err(format!("unknown instruction {mnemonic}")),
+        };
+
+        rom.extend(op.to_be_bytes());
+
+        Ok(())
+    }
+}
+
+// Assembles a program to be loaded at START_ADDR, where `Emu::load` puts it
+pub fn assemble(src: &str) -> Result<Vec<u8>, AsmError> {
+    let mut labels = BTreeMap::new();
+    let mut statements = Vec::new();
+    let mut addr = START_ADDR as u32;
+
+    // First pass: where every label is, which only needs the size of each statement
+    for (idx, line) in src.lines().enumerate() {
+        let error = |message| AsmError {
+            line: idx + 1,
+            message,
+        };
+
+        let mut code = line.split(';').next().unwrap_or_default().trim();
+
+        if let Some((label, rest)) = code.split_once(':') {
+            let label = label.trim();
+
+            if !is_label(label) {
+                return Err(error(format!("invalid label {label}")));
+            }
+
+            if labels.insert(label, addr).is_some() {
+                return Err(error(format!("label {label} is defined twice")));
+            }
+
+            code = rest.trim();
+        }
+
+        if code.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operands) = match code.split_once(char::is_whitespace) {
+            Some((mnemonic, operands)) => (mnemonic, operands.split(',').map(str::trim).collect()),
+            None => (code, Vec::new()),
+        };
+
+        let statement = Statement {
+            line: idx + 1,
+            mnemonic: mnemonic.to_ascii_uppercase(),
+            operands,
+        };
+
+        addr += statement.size() as u32;
+        statements.push(statement);
+    }
+
+    // Second pass: encode everything, now that labels used before their definition are known
+    let mut rom = Vec::new();
+
+    for statement in &statements {
+        statement
+            .encode(&labels, &mut rom)
+            .map_err(|message| AsmError {
+                line: statement.line,
+                message,
+            })?;
+    }
+
+    Ok(rom)
+}
+
+// For `LONG` or `LONG addr`, the address if there is one
+fn long_address(operand: &str) -> Option<Option<&str>> {
+    match operand.split_once(char::is_whitespace) {
+        Some((long, addr)) if long.eq_ignore_ascii_case("LONG") => Some(Some(addr.trim())),
+        None if operand.eq_ignore_ascii_case("LONG") => Some(None),
+        _ => None,
+    }
+}
+
+fn is_label(label: &str) -> bool {
+    let mut chars = label.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// The value of a number or label, which has to fit in `max`
+fn resolve(text: &str, labels: &BTreeMap<&str, u32>, max: u32) -> Result<u32, String> {
+    let number = if let Some(digits) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        u32::from_str_radix(digits, 16).ok()
+    } else if let Some(digits) = text.strip_prefix("0b").or(text.strip_prefix("0B")) {
+        u32::from_str_radix(digits, 2).ok()
+    } else {
+        text.parse().ok()
+    };
+
+    let value = match number {
+        Some(value) => value,
+        None if is_label(text) => *labels
+            .get(text)
+            .ok_or_else(|| format!("unknown label {text}"))?,
+        None => return Err(format!("invalid number {text}")),
+    };
+
+    if value > max {
+        return Err(format!("{text} is out of range, the most is {max:#X}"));
+    }
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec};
+
+    use super::*;
+    use crate::{disasm::disassemble, testing::run};
+
+    #[test]
+    fn assembles_every_disassembly() {
+        for op in (0..=u16::MAX).filter(|&op| op != 0xF000) {
+            let src = disassemble(op);
+
+            assert_eq!(assemble(&src), Ok(op.to_be_bytes().to_vec()), "{src}");
+        }
+
+        assert_eq!(
+            assemble("LD I, LONG 0x1234"),
+            Ok(vec![0xF0, 0x00, 0x12, 0x34])
+        );
+    }
+
+    #[test]
+    fn resolves_forward_labels() {
+        let src = "
+            JP end
+            LD V0, 1        ; skipped
+            end: LD I, sprite
+            sprite: DB 0xF0
+        ";
+
+        assert_eq!(
+            assemble(src),
+            Ok(vec![0x12, 0x04, 0x60, 0x01, 0xA2, 0x06, 0xF0])
+        );
+
+        let emu = run(src, 2);
+
+        assert_eq!(emu.registers()[0], 0);
+        assert_eq!(emu.i_register(), 0x206);
+    }
+
+    #[test]
+    fn reports_errors_by_line() {
+        let error = |line, message: &str| {
+            Err(AsmError {
+                line,
+                message: message.to_string(),
+            })
+        };
+
+        assert_eq!(
+            assemble("a: CLS\na: CLS"),
+            error(2, "label a is defined twice")
+        );
+        assert_eq!(assemble("CLS\nFOO V0"), error(2, "unknown instruction FOO"));
+        assert!(assemble("JP nowhere").is_err());
+        assert!(assemble("LD V0, 256").is_err());
+    }
+}
@@ -0,0 +1,228 @@
+use std::f32::consts::TAU;
+
+use chip8_emu::constants::AUDIO_PATTERN_SIZE;
+use sdl2::{
+    AudioSubsystem,
+    audio::{AudioCallback, AudioDevice, AudioSpecDesired},
+};
+
+const SAMPLE_RATE: i32 = 44100;
+pub const BEEP_FREQ: f32 = 440.0;
+const BEEP_VOLUME: f32 = 0.25;
+
+// How much the volume keys change the volume by
+pub const VOLUME_STEP: f32 = 0.05;
+
+const PATTERN_BITS: f32 = (AUDIO_PATTERN_SIZE * 8) as f32;
+
+// Shape of the beep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Waveform {
+    #[default]
+    Square,
+    Sine,
+    Triangle,
+    Sawtooth,
+}
+
+const WAVEFORMS: [(&str, Waveform); 4] = [
+    ("square", Waveform::Square),
+    ("sine", Waveform::Sine),
+    ("triangle", Waveform::Triangle),
+    ("sawtooth", Waveform::Sawtooth),
+];
+
+impl Waveform {
+    pub fn from_name(name: &str) -> Option<Self> {
+        WAVEFORMS
+            .iter()
+            .find(|(waveform_name, _)| *waveform_name == name)
+            .map(|&(_, waveform)| waveform)
+    }
+
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        WAVEFORMS.iter().map(|&(name, _)| name)
+    }
+
+    // Level from -1 to 1 at a phase from 0 to 1 through a period
+    pub fn sample(self, phase: f32) -> f32 {
+        match self {
+            // High for the first half of each period, low for the second
+            Waveform::Square => {
+                if phase <= 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (phase * TAU).sin(),
+            // Down from 1 to -1 over the first half, then back up
+            Waveform::Triangle => (phase - 0.5).abs() * 4.0 - 1.0,
+            Waveform::Sawtooth => phase * 2.0 - 1.0,
+        }
+    }
+}
+
+// A beep, or an XO-CHIP audio pattern once the game has loaded one
+pub struct Tone {
+    sample_rate: f32,
+    waveform: Waveform,
+    beep_freq: f32,
+    phase: f32,
+    // From 0 (silent) to 1 (full scale)
+    volume: f32,
+    muted: bool,
+    pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    playback_rate: f32,
+}
+
+impl Tone {
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    // Muting keeps the volume, for when it's unmuted
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn set_pattern(&mut self, pattern: Option<[u8; AUDIO_PATTERN_SIZE]>, playback_rate: f32) {
+        self.pattern = pattern;
+        self.playback_rate = playback_rate;
+    }
+}
+
+impl AudioCallback for Tone {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            // The phase is how far through the pattern, or a period of the beep, this sample is
+            let level = match self.pattern {
+                Some(pattern) => {
+                    let bit = (self.phase * PATTERN_BITS) as usize;
+
+                    if pattern[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                }
+                None => self.waveform.sample(self.phase),
+            };
+
+            let volume = if self.muted { 0.0 } else { self.volume };
+            *sample = level * volume;
+
+            let phase_inc = match self.pattern {
+                Some(_) => self.playback_rate / PATTERN_BITS / self.sample_rate,
+                None => self.beep_freq / self.sample_rate,
+            };
+
+            self.phase = (self.phase + phase_inc) % 1.0;
+        }
+    }
+}
+
+// Opens a paused audio device playing a constant beep. The game loop resumes/pauses it to
+// follow the sound timer, and passes on any audio pattern.
+pub fn open_beeper(
+    audio_subsystem: &AudioSubsystem,
+    waveform: Waveform,
+    beep_freq: f32,
+) -> Result<AudioDevice<Tone>, String> {
+    let desired_spec = AudioSpecDesired {
+        freq: Some(SAMPLE_RATE),
+        channels: Some(1),
+        samples: None,
+    };
+
+    audio_subsystem.open_playback(None, &desired_spec, |spec| Tone {
+        sample_rate: spec.freq as f32,
+        waveform,
+        beep_freq,
+        phase: 0.0,
+        volume: BEEP_VOLUME,
+        muted: false,
+        pattern: None,
+        playback_rate: 0.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_waveforms() {
+        let samples =
+            |waveform: Waveform| [0.0, 0.25, 0.5, 0.75].map(|phase| waveform.sample(phase));
+
+        assert_eq!(samples(Waveform::Square), [1.0, 1.0, 1.0, -1.0]);
+        assert_eq!(samples(Waveform::Triangle), [1.0, 0.0, -1.0, 0.0]);
+        assert_eq!(samples(Waveform::Sawtooth), [-1.0, -0.5, 0.0, 0.5]);
+
+        let sine = samples(Waveform::Sine);
+        for (sample, expected) in sine.into_iter().zip([0.0, 1.0, 0.0, -1.0]) {
+            assert!((sample - expected).abs() < 1e-6, "{sine:?}");
+        }
+    }
+
+    #[test]
+    fn names_waveforms() {
+        assert!(Waveform::names().all(|name| Waveform::from_name(name).is_some()));
+        assert_eq!(Waveform::from_name("sine"), Some(Waveform::Sine));
+        assert_eq!(Waveform::from_name("noise"), None);
+    }
+
+    // A tone at a sample rate of 8 per period of the beep
+    fn tone() -> Tone {
+        Tone {
+            sample_rate: 8.0,
+            waveform: Waveform::Sawtooth,
+            beep_freq: 1.0,
+            phase: 0.0,
+            volume: 0.5,
+            muted: false,
+            pattern: None,
+            playback_rate: 0.0,
+        }
+    }
+
+    #[test]
+    fn plays_beeps_at_the_volume() {
+        let mut tone = tone();
+        let mut out = [0.0; 8];
+        tone.callback(&mut out);
+
+        assert_eq!(out, [-0.5, -0.375, -0.25, -0.125, 0.0, 0.125, 0.25, 0.375]);
+
+        tone.set_muted(true);
+        tone.callback(&mut out);
+
+        assert!(out.iter().all(|&sample| sample == 0.0));
+        assert_eq!(tone.volume(), 0.5);
+    }
+
+    #[test]
+    fn plays_patterns_a_bit_at_a_time() {
+        let mut pattern = [0; AUDIO_PATTERN_SIZE];
+        pattern[0] = 0b1010_0000;
+
+        // A bit per sample
+        let mut tone = tone();
+        tone.set_pattern(Some(pattern), 8.0);
+        let mut out = [0.0; 4];
+        tone.callback(&mut out);
+
+        assert_eq!(out, [0.5, -0.5, 0.5, -0.5]);
+    }
+}
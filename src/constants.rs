@@ -4,11 +4,22 @@ pub const RAM_SIZE: usize = 4096;
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
 
+// SUPER-CHIP high resolution mode
+pub const HIRES_SCREEN_WIDTH: usize = 128;
+pub const HIRES_SCREEN_HEIGHT: usize = 64;
+
 pub const NUM_REGS: usize = 16;
 pub const NUM_KEYS: usize = 16;
+pub const NUM_RPL_FLAGS: usize = 8;
+
+// XO-CHIP bit planes, for 4 colors
+pub const NUM_PLANES: usize = 2;
 
 pub const STACK_SIZE: usize = 16;
 
+// XO-CHIP audio patterns are 16 bytes, played as 128 1-bit samples
+pub const AUDIO_PATTERN_SIZE: usize = 16;
+
 pub const START_ADDR: u16 = 0x200;
 
 pub const FONTSET_SIZE: usize = 16 * 5; // 16 characters, 5 bytes each
@@ -32,9 +43,33 @@ pub const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+// SUPER-CHIP large font, stored in RAM right after the small one
+pub const LARGE_FONTSET_ADDR: usize = FONTSET_SIZE;
+pub const LARGE_FONTSET_SIZE: usize = 10 * 10; // 10 digits, 10 bytes each
+
+pub const LARGE_FONTSET: [u8; LARGE_FONTSET_SIZE] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+];
+
+pub const TICKS_PER_FRAME: usize = 10;
+
+// The delay and sound timers count down at 60Hz, and a frame is run for each of their ticks
+pub const TIMER_FREQ: u32 = 60;
+
+// Frames of history the frontend keeps for rewinding, 10 seconds at 60 FPS
+pub const DEFAULT_REWIND_DEPTH: usize = 600;
+
 // SDL2 specific
+pub const WINDOW_TITLE: &str = "CHIP-8 Emulator";
 pub const SCALE: u32 = 15;
 pub const WINDOW_WIDTH: u32 = (SCREEN_WIDTH as u32) * SCALE;
 pub const WINDOW_HEIGHT: u32 = (SCREEN_HEIGHT as u32) * SCALE;
-
-pub const TICKS_PER_FRAME: usize = 10;
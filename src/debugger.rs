@@ -0,0 +1,728 @@
+use alloc::{
+    boxed::Box,
+    collections::BTreeSet,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt, str::FromStr};
+
+use crate::emu::{Emu, EmuError, RamWrite};
+
+// Something a condition can look at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reg {
+    V(usize),
+    I,
+    Pc,
+    Dt,
+    St,
+}
+
+impl Reg {
+    pub fn read(self, emu: &Emu) -> u16 {
+        match self {
+            Reg::V(x) => emu.registers()[x] as u16,
+            Reg::I => emu.i_register(),
+            Reg::Pc => emu.program_counter(),
+            Reg::Dt => emu.delay_timer() as u16,
+            Reg::St => emu.sound_timer() as u16,
+        }
+    }
+}
+
+impl FromStr for Reg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "I" => Ok(Reg::I),
+            "PC" => Ok(Reg::Pc),
+            "DT" => Ok(Reg::Dt),
+            "ST" => Ok(Reg::St),
+            name => name
+                .strip_prefix('V')
+                .filter(|x| x.len() == 1)
+                .and_then(|x| usize::from_str_radix(x, 16).ok())
+                .map(Reg::V)
+                .ok_or(format!("unknown register {s}")),
+        }
+    }
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reg::V(x) => write!(f, "V{x:X}"),
+            Reg::I => write!(f, "I"),
+            Reg::Pc => write!(f, "PC"),
+            Reg::Dt => write!(f, "DT"),
+            Reg::St => write!(f, "ST"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+// Breaks when `reg op value` becomes true, e.g. V3 == 0x0A or I >= 0x300
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub reg: Reg,
+    pub op: CmpOp,
+    pub value: u16,
+}
+
+impl Condition {
+    pub fn holds(&self, emu: &Emu) -> bool {
+        let reg_value = self.reg.read(emu);
+
+        match self.op {
+            CmpOp::Eq => reg_value == self.value,
+            CmpOp::Ne => reg_value != self.value,
+            CmpOp::Lt => reg_value < self.value,
+            CmpOp::Le => reg_value <= self.value,
+            CmpOp::Gt => reg_value > self.value,
+            CmpOp::Ge => reg_value >= self.value,
+        }
+    }
+}
+
+// Parses conditions like "V3 == 0A" or "I>=0x300". Values are hex, like addresses.
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Two character operators first, so <= isn't read as <
+        const OPS: [(&str, CmpOp); 6] = [
+            ("==", CmpOp::Eq),
+            ("!=", CmpOp::Ne),
+            ("<=", CmpOp::Le),
+            (">=", CmpOp::Ge),
+            ("<", CmpOp::Lt),
+            (">", CmpOp::Gt),
+        ];
+
+        let (reg, op, value) = OPS
+            .iter()
+            .find_map(|&(symbol, op)| {
+                s.split_once(symbol)
+                    .map(|(reg, value)| (reg.trim(), op, value.trim()))
+            })
+            .ok_or("expected REG OP VALUE, e.g. V3 == 0A")?;
+
+        let reg = reg.parse()?;
+        let value = parse_hex(value).ok_or(format!("invalid value {value}"))?;
+
+        Ok(Condition { reg, op, value })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            CmpOp::Eq => "==",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+        };
+
+        write!(f, "{} {op} {:#X}", self.reg, self.value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl BinOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+        }
+    }
+
+    // * and / bind tighter than + and -
+    fn precedence(self) -> u8 {
+        match self {
+            BinOp::Add | BinOp::Sub => 0,
+            BinOp::Mul | BinOp::Div => 1,
+        }
+    }
+}
+
+// Something to print after every step, e.g. V0 + V1, mem[I] or [0x300]. Numbers are hex, like
+// everywhere else in the debugger, and arithmetic wraps around at 16 bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Num(u16),
+    Reg(Reg),
+    // The byte at an address, written mem[ADDR] or [ADDR]
+    Mem(Box<Expr>),
+    Bin(BinOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn eval(&self, emu: &Emu) -> Result<u16, String> {
+        Ok(match self {
+            Expr::Num(value) => *value,
+            Expr::Reg(reg) => reg.read(emu),
+            Expr::Mem(addr) => emu.peek(addr.eval(emu)?).map_err(|err| err.to_string())? as u16,
+            Expr::Bin(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(emu)?, rhs.eval(emu)?);
+
+                match op {
+                    BinOp::Add => lhs.wrapping_add(rhs),
+                    BinOp::Sub => lhs.wrapping_sub(rhs),
+                    BinOp::Mul => lhs.wrapping_mul(rhs),
+                    BinOp::Div => lhs.checked_div(rhs).ok_or("division by zero")?,
+                }
+            }
+        })
+    }
+}
+
+// A recursive descent parser, one function per precedence level
+struct ExprParser<'a> {
+    tokens: Vec<&'a str>,
+    pos: usize,
+}
+
+impl<'a> ExprParser<'a> {
+    // Names and numbers are runs of letters and digits, and everything else but spaces is a
+    // token of its own
+    fn new(s: &'a str) -> Self {
+        let mut tokens = Vec::new();
+        let mut rest = s.trim_start();
+
+        while let Some(c) = rest.chars().next() {
+            let len = if c.is_ascii_alphanumeric() {
+                rest.find(|c: char| !c.is_ascii_alphanumeric())
+                    .unwrap_or(rest.len())
+            } else {
+                c.len_utf8()
+            };
+
+            tokens.push(&rest[..len]);
+            rest = rest[len..].trim_start();
+        }
+
+        Self { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected {expected}, got {token}")),
+            None => Err(format!("expected {expected}")),
+        }
+    }
+
+    // Either level of binary operators, lowest first
+    fn binary(&mut self, precedence: u8) -> Result<Expr, String> {
+        let operand = |parser: &mut Self| {
+            if precedence == 0 {
+                parser.binary(1)
+            } else {
+                parser.atom()
+            }
+        };
+
+        let mut expr = operand(self)?;
+
+        while let Some(op) = self.peek().and_then(|token| {
+            [BinOp::Add, BinOp::Sub, BinOp::Mul, BinOp::Div]
+                .into_iter()
+                .find(|op| op.precedence() == precedence && token == op.symbol())
+        }) {
+            self.pos += 1;
+            expr = Expr::Bin(op, Box::new(expr), Box::new(operand(self)?));
+        }
+
+        Ok(expr)
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self.next().ok_or("expected a value")?;
+
+        match token {
+            "(" => {
+                let expr = self.binary(0)?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            "[" => self.memory(),
+            _ if token.eq_ignore_ascii_case("mem") => {
+                self.expect("[")?;
+                self.memory()
+            }
+            _ => match token.parse() {
+                Ok(reg) => Ok(Expr::Reg(reg)),
+                Err(_) => parse_hex(token)
+                    .map(Expr::Num)
+                    .ok_or(format!("invalid value {token}")),
+            },
+        }
+    }
+
+    // What's left of mem[ADDR] after the opening bracket
+    fn memory(&mut self) -> Result<Expr, String> {
+        let addr = self.binary(0)?;
+        self.expect("]")?;
+
+        Ok(Expr::Mem(Box::new(addr)))
+    }
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = ExprParser::new(s);
+        let expr = parser.binary(0)?;
+
+        match parser.peek() {
+            Some(token) => Err(format!("unexpected {token}")),
+            None => Ok(expr),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Num(value) => write!(f, "{value:#X}"),
+            Expr::Reg(reg) => write!(f, "{reg}"),
+            Expr::Mem(addr) => write!(f, "[{addr}]"),
+            Expr::Bin(op, lhs, rhs) => {
+                // Brackets only where they change the meaning: around looser operators, and
+                // on the right of the same level, e.g. V0 - (V1 - V2)
+                let wrap = |expr: &Expr, on_right: bool| match expr {
+                    Expr::Bin(inner, ..) => {
+                        inner.precedence() < op.precedence()
+                            || on_right && inner.precedence() == op.precedence()
+                    }
+                    _ => false,
+                };
+
+                for (expr, on_right) in [(lhs, false), (rhs, true)] {
+                    if on_right {
+                        write!(f, " {} ", op.symbol())?;
+                    }
+
+                    if wrap(expr, on_right) {
+                        write!(f, "({expr})")?;
+                    } else {
+                        write!(f, "{expr}")?;
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+// Hex, with or without 0x in front
+pub fn parse_hex(s: &str) -> Option<u16> {
+    let digits = s.trim_start_matches("0x").trim_start_matches("0X");
+
+    u16::from_str_radix(digits, 16).ok()
+}
+
+// Drives an `Emu` one instruction at a time, stopping before any instruction at a breakpoint
+// address, after a write to a watched address, or once a condition becomes true. Timers still
+// tick at the usual rate relative to instructions executed.
+#[derive(Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+
+    // Addresses to stop after any write to
+    watchpoints: BTreeSet<u16>,
+    watch_hit: Option<RamWrite>,
+
+    // Along with whether each held after the last step, so they only fire when they go from
+    // false to true rather than on every step while they stay true
+    conditions: Vec<(Condition, bool)>,
+    condition_hit: bool,
+
+    // Expressions shown after every step
+    displays: Vec<Expr>,
+
+    // Instructions executed since the last timer tick
+    ticks: usize,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    // Returns whether there was a breakpoint at addr
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn add_watchpoint(&mut self, addr: u16) {
+        self.watchpoints.insert(addr);
+    }
+
+    pub fn remove_watchpoint(&mut self, addr: u16) -> bool {
+        self.watchpoints.remove(&addr)
+    }
+
+    pub fn watchpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.watchpoints.iter().copied()
+    }
+
+    // The write to a watched address made by the last step, if there was one
+    pub fn watch_hit(&self) -> Option<RamWrite> {
+        self.watch_hit
+    }
+
+    pub fn add_condition(&mut self, condition: Condition) {
+        self.conditions.push((condition, false));
+    }
+
+    // Conditions are numbered in the order they were added. Returns whether there was one at
+    // index.
+    pub fn remove_condition(&mut self, index: usize) -> bool {
+        if index < self.conditions.len() {
+            self.conditions.remove(index);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn conditions(&self) -> impl Iterator<Item = &Condition> + '_ {
+        self.conditions.iter().map(|(condition, _)| condition)
+    }
+
+    pub fn add_display(&mut self, expr: Expr) {
+        self.displays.push(expr);
+    }
+
+    // Numbered like conditions
+    pub fn remove_display(&mut self, index: usize) -> bool {
+        if index < self.displays.len() {
+            self.displays.remove(index);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn displays(&self) -> impl Iterator<Item = &Expr> + '_ {
+        self.displays.iter()
+    }
+
+    pub fn at_breakpoint(&self, emu: &Emu) -> bool {
+        self.condition_hit
+            || self.watch_hit.is_some()
+            || self.breakpoints.contains(&emu.program_counter())
+    }
+
+    // Execute exactly one instruction, whether or not the PC is at a breakpoint. The timers
+    // tick once every `Emu::ticks_per_frame` instructions, as they would running normally.
+    pub fn step(&mut self, emu: &mut Emu) -> Result<(), EmuError> {
+        emu.tick()?;
+
+        self.ticks += 1;
+
+        if self.ticks >= emu.ticks_per_frame() {
+            emu.tick_timers();
+            self.ticks = 0;
+        }
+
+        self.watch_hit = emu
+            .last_writes()
+            .iter()
+            .find(|write| self.watchpoints.contains(&write.addr))
+            .copied();
+
+        self.condition_hit = false;
+
+        for (condition, held) in &mut self.conditions {
+            let holds = condition.holds(emu);
+            self.condition_hit |= holds && !*held;
+            *held = holds;
+        }
+
+        Ok(())
+    }
+
+    // Run until the PC reaches a breakpoint, leaving the instruction there unexecuted. The
+    // current instruction always runs, so continuing from a breakpoint moves past it.
+    pub fn continue_run(&mut self, emu: &mut Emu) -> Result<(), EmuError> {
+        loop {
+            self.step(emu)?;
+
+            if self.at_breakpoint(emu) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::run;
+
+    const COUNTER: &str = "
+        LD V0, 0
+        loop: ADD V0, 1
+        JP loop
+    ";
+
+    // Evaluated with V0 = 2, V1 = 3 and I pointing at 0x42
+    fn eval(expr: &str) -> Result<u16, String> {
+        let emu = run(
+            "
+            LD V0, 0x42
+            LD I, 0x300
+            LD [I], V0
+            LD V0, 2
+            LD V1, 3
+            ",
+            5,
+        );
+
+        expr.parse::<Expr>()?.eval(&emu)
+    }
+
+    #[test]
+    fn evaluates_with_precedence() {
+        assert_eq!(eval("V0 + V1 * 4"), Ok(14));
+        assert_eq!(eval("(V0 + V1) * 4"), Ok(20));
+        assert_eq!(eval("10 - 4 - 2"), Ok(0xA));
+        assert_eq!(eval("V1 / V0"), Ok(1));
+        assert_eq!(eval("0 - 1"), Ok(0xFFFF));
+    }
+
+    #[test]
+    fn reads_memory() {
+        assert_eq!(eval("mem[I]"), Ok(0x42));
+        assert_eq!(eval("[0x300]"), Ok(0x42));
+        assert_eq!(eval("[I + 1] + V0"), Ok(2));
+    }
+
+    #[test]
+    fn rejects_division_by_zero() {
+        assert_eq!(eval("V0 / 0"), Err("division by zero".to_string()));
+        assert_eq!(eval("V0 / (V1 - 3)"), Err("division by zero".to_string()));
+    }
+
+    #[test]
+    fn rejects_bad_expressions() {
+        for expr in ["V0 +", "[I", "mem I", "V0 V1", "VG"] {
+            assert!(expr.parse::<Expr>().is_err(), "{expr}");
+        }
+    }
+
+    #[test]
+    fn displays_expressions() {
+        for expr in [
+            "V0 + V1 * 0x4",
+            "(V0 + V1) * 0x4",
+            "V0 - (V1 - 0x2)",
+            "[I + 0x1]",
+        ] {
+            assert_eq!(expr.parse::<Expr>().unwrap().to_string(), expr);
+        }
+    }
+
+    #[test]
+    fn stops_at_breakpoints() {
+        let mut emu = run(COUNTER, 0);
+        let mut debugger = Debugger::new();
+        debugger.add_breakpoint(0x204);
+
+        // The ADD runs, and the JP it stopped at doesn't
+        debugger.continue_run(&mut emu).unwrap();
+
+        assert_eq!(emu.program_counter(), 0x204);
+        assert_eq!(emu.registers()[0], 1);
+
+        // Continuing from the breakpoint goes around the loop once more
+        debugger.continue_run(&mut emu).unwrap();
+
+        assert_eq!(emu.program_counter(), 0x204);
+        assert_eq!(emu.registers()[0], 2);
+
+        assert!(debugger.remove_breakpoint(0x204));
+        assert!(!debugger.remove_breakpoint(0x204));
+    }
+
+    #[test]
+    fn steps_one_instruction() {
+        let mut emu = run(COUNTER, 0);
+        let mut debugger = Debugger::new();
+        debugger.add_breakpoint(0x202);
+
+        assert!(!debugger.at_breakpoint(&emu));
+
+        debugger.step(&mut emu).unwrap();
+
+        assert!(debugger.at_breakpoint(&emu));
+
+        // Stepping ignores the breakpoint it's at
+        debugger.step(&mut emu).unwrap();
+
+        assert_eq!(emu.program_counter(), 0x204);
+    }
+
+    #[test]
+    fn stops_when_conditions_become_true() {
+        let mut emu = run(COUNTER, 0);
+        let mut debugger = Debugger::new();
+        debugger.add_condition("V0 >= 0A".parse().unwrap());
+
+        debugger.continue_run(&mut emu).unwrap();
+
+        // Right after the ADD that got there
+        assert_eq!(emu.registers()[0], 0xA);
+        assert_eq!(emu.program_counter(), 0x204);
+
+        // Not again while it stays true
+        debugger.step(&mut emu).unwrap();
+        debugger.step(&mut emu).unwrap();
+
+        assert_eq!(emu.registers()[0], 0xB);
+        assert!(!debugger.at_breakpoint(&emu));
+
+        assert_eq!(
+            debugger
+                .conditions()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["V0 >= 0xA"]
+        );
+        assert!(debugger.remove_condition(0));
+        assert!(!debugger.remove_condition(0));
+    }
+
+    #[test]
+    fn parses_conditions() {
+        assert_eq!(
+            "I>=0x300".parse(),
+            Ok(Condition {
+                reg: Reg::I,
+                op: CmpOp::Ge,
+                value: 0x300
+            })
+        );
+        assert_eq!(
+            "vf != 1".parse(),
+            Ok(Condition {
+                reg: Reg::V(0xF),
+                op: CmpOp::Ne,
+                value: 1
+            })
+        );
+
+        for condition in ["V0 = 1", "VG == 1", "V0 == ZZ", "V0"] {
+            assert!(condition.parse::<Condition>().is_err(), "{condition}");
+        }
+    }
+
+    #[test]
+    fn stops_after_writes_to_watched_addresses() {
+        let mut emu = run(
+            "
+            LD I, 0x300
+            LD V0, 1
+            LD V1, 2
+            LD [I], V1
+            LD B, V1
+            loop: JP loop
+            ",
+            0,
+        );
+        let mut debugger = Debugger::new();
+        debugger.add_watchpoint(0x301);
+
+        debugger.continue_run(&mut emu).unwrap();
+
+        // After the FX55, with what it wrote
+        assert_eq!(emu.program_counter(), 0x208);
+        assert_eq!(
+            debugger.watch_hit(),
+            Some(RamWrite {
+                addr: 0x301,
+                old: 0,
+                new: 2
+            })
+        );
+
+        // FX33 writes the tens digit there
+        debugger.continue_run(&mut emu).unwrap();
+
+        assert_eq!(emu.program_counter(), 0x20A);
+        assert_eq!(
+            debugger.watch_hit(),
+            Some(RamWrite {
+                addr: 0x301,
+                old: 2,
+                new: 0
+            })
+        );
+
+        // Steps that write nowhere watched clear it
+        debugger.step(&mut emu).unwrap();
+        assert_eq!(debugger.watch_hit(), None);
+        assert!(debugger.remove_watchpoint(0x301));
+    }
+
+    #[test]
+    fn ticks_timers_at_the_emulator_speed() {
+        let mut emu = run("LD V0, 10\nLD DT, V0\nloop: JP loop", 2);
+        emu.set_ticks_per_frame(4);
+        let mut debugger = Debugger::new();
+
+        for _ in 0..3 {
+            debugger.step(&mut emu).unwrap();
+        }
+
+        assert_eq!(emu.delay_timer(), 10);
+
+        debugger.step(&mut emu).unwrap();
+
+        assert_eq!(emu.delay_timer(), 9);
+    }
+}
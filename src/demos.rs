@@ -0,0 +1,21 @@
+// Small ROMs built into the binary, so there's something to run without finding a game first.
+// They were written for this repo and are in the public domain.
+pub const DEMOS: [(&str, &str, &[u8]); 2] = [
+    (
+        "keys",
+        "shows the last key pressed",
+        include_bytes!("../roms/keys.ch8"),
+    ),
+    (
+        "bounce",
+        "a ball bouncing around the screen",
+        include_bytes!("../roms/bounce.ch8"),
+    ),
+];
+
+pub fn find(name: &str) -> Option<&'static [u8]> {
+    DEMOS
+        .iter()
+        .find(|(demo_name, _, _)| *demo_name == name)
+        .map(|&(_, _, rom)| rom)
+}
@@ -0,0 +1,578 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::constants::START_ADDR;
+
+// Mnemonics mostly follow Cowgod's specification, plus the SCHIP and XO-CHIP additions
+pub fn disassemble(op: u16) -> String {
+    let nibbles = [op >> 12, op >> 8, op >> 4, op].map(|nibble| (nibble & 0xF) as u8);
+
+    let x = nibbles[1];
+    let y = nibbles[2];
+    let n = nibbles[3];
+    let nn = op & 0xFF;
+    let nnn = op & 0xFFF;
+
+    match nibbles {
+        [0, 0, 0, 0] => "NOP".to_string(),
+        [0, 0, 0xC, _] => format!("SCD {n}"),
+        [0, 0, 0xE, 0] => "CLS".to_string(),
+        [0, 0, 0xE, 0xE] => "RET".to_string(),
+        [0, 0, 0xF, 0xB] => "SCR".to_string(),
+        [0, 0, 0xF, 0xC] => "SCL".to_string(),
+        [0, 0, 0xF, 0xE] => "LOW".to_string(),
+        [0, 0, 0xF, 0xF] => "HIGH".to_string(),
+        [1, _, _, _] => format!("JP {nnn:#05X}"),
+        [2, _, _, _] => format!("CALL {nnn:#05X}"),
+        [3, _, _, _] => format!("SE V{x}, {nn:#04X}"),
+        [4, _, _, _] => format!("SNE V{x}, {nn:#04X}"),
+        [5, _, _, 0] => format!("SE V{x}, V{y}"),
+        [6, _, _, _] => format!("LD V{x}, {nn:#04X}"),
+        [7, _, _, _] => format!("ADD V{x}, {nn:#04X}"),
+        [8, _, _, 0] => format!("LD V{x}, V{y}"),
+        [8, _, _, 1] => format!("OR V{x}, V{y}"),
+        [8, _, _, 2] => format!("AND V{x}, V{y}"),
+        [8, _, _, 3] => format!("XOR V{x}, V{y}"),
+        [8, _, _, 4] => format!("ADD V{x}, V{y}"),
+        [8, _, _, 5] => format!("SUB V{x}, V{y}"),
+        [8, _, _, 6] => format!("SHR V{x}, V{y}"),
+        [8, _, _, 7] => format!("SUBN V{x}, V{y}"),
+        [8, _, _, 0xE] => format!("SHL V{x}, V{y}"),
+        [9, _, _, 0] => format!("SNE V{x}, V{y}"),
+        [0xA, _, _, _] => format!("LD I, {nnn:#05X}"),
+        [0xB, _, _, _] => format!("JP V0, {nnn:#05X}"),
+        [0xC, _, _, _] => format!("RND V{x}, {nn:#04X}"),
+        [0xD, _, _, _] => format!("DRW V{x}, V{y}, {n}"),
+        [0xE, _, 9, 0xE] => format!("SKP V{x}"),
+        [0xE, _, 0xA, 1] => format!("SKNP V{x}"),
+        // The address is in the next word
+        [0xF, 0, 0, 0] => "LD I, LONG".to_string(),
+        [0xF, _, 0, 1] => format!("PLANE {x}"),
+        [0xF, 0, 0, 2] => "AUDIO".to_string(),
+        [0xF, _, 0, 7] => format!("LD V{x}, DT"),
+        [0xF, _, 0, 0xA] => format!("LD V{x}, K"),
+        [0xF, _, 1, 5] => format!("LD DT, V{x}"),
+        [0xF, _, 1, 8] => format!("LD ST, V{x}"),
+        [0xF, _, 1, 0xE] => format!("ADD I, V{x}"),
+        [0xF, _, 2, 9] => format!("LD F, V{x}"),
+        [0xF, _, 3, 0] => format!("LD HF, V{x}"),
+        [0xF, _, 3, 3] => format!("LD B, V{x}"),
+        [0xF, _, 3, 0xA] => format!("PITCH V{x}"),
+        [0xF, _, 5, 5] => format!("LD [I], V{x}"),
+        [0xF, _, 6, 5] => format!("LD V{x}, [I]"),
+        [0xF, _, 7, 5] => format!("LD R, V{x}"),
+        [0xF, _, 8, 5] => format!("LD V{x}, R"),
+
+        // Not an instruction, most likely sprite or other data
+        [_, _, _, _] => format!("DW {op:#06X}"),
+    }
+}
+
+// Instruction families as `opcode_family` numbers them, with anything unrecognized last
+pub const OPCODE_FAMILIES: [&str; 48] = [
+    "0000 NOP",
+    "00CN SCD",
+    "00E0 CLS",
+    "00EE RET",
+    "00FB SCR",
+    "00FC SCL",
+    "00FE LOW",
+    "00FF HIGH",
+    "1NNN JP",
+    "2NNN CALL",
+    "3XNN SE",
+    "4XNN SNE",
+    "5XY0 SE",
+    "6XNN LD",
+    "7XNN ADD",
+    "8XY0 LD",
+    "8XY1 OR",
+    "8XY2 AND",
+    "8XY3 XOR",
+    "8XY4 ADD",
+    "8XY5 SUB",
+    "8XY6 SHR",
+    "8XY7 SUBN",
+    "8XYE SHL",
+    "9XY0 SNE",
+    "ANNN LD I",
+    "BNNN JP V0",
+    "CXNN RND",
+    "DXYN DRW",
+    "EX9E SKP",
+    "EXA1 SKNP",
+    "F000 LD I, LONG",
+    "FN01 PLANE",
+    "F002 AUDIO",
+    "FX07 LD Vx, DT",
+    "FX0A LD Vx, K",
+    "FX15 LD DT",
+    "FX18 LD ST",
+    "FX1E ADD I",
+    "FX29 LD F",
+    "FX30 LD HF",
+    "FX33 LD B",
+    "FX3A PITCH",
+    "FX55 LD [I]",
+    "FX65 LD Vx, [I]",
+    "FX75 LD R",
+    "FX85 LD Vx, R",
+    "unknown",
+];
+
+// Index into OPCODE_FAMILIES of the instruction an opcode is
+pub fn opcode_family(op: u16) -> usize {
+    let nibbles = [op >> 12, op >> 8, op >> 4, op].map(|nibble| (nibble & 0xF) as u8);
+
+    match nibbles {
+        [0, 0, 0, 0] => 0,
+        [0, 0, 0xC, _] => 1,
+        [0, 0, 0xE, 0] => 2,
+        [0, 0, 0xE, 0xE] => 3,
+        [0, 0, 0xF, 0xB] => 4,
+        [0, 0, 0xF, 0xC] => 5,
+        [0, 0, 0xF, 0xE] => 6,
+        [0, 0, 0xF, 0xF] => 7,
+        [1, _, _, _] => 8,
+        [2, _, _, _] => 9,
+        [3, _, _, _] => 10,
+        [4, _, _, _] => 11,
+        [5, _, _, 0] => 12,
+        [6, _, _, _] => 13,
+        [7, _, _, _] => 14,
+        [8, _, _, 0] => 15,
+        [8, _, _, 1] => 16,
+        [8, _, _, 2] => 17,
+        [8, _, _, 3] => 18,
+        [8, _, _, 4] => 19,
+        [8, _, _, 5] => 20,
+        [8, _, _, 6] => 21,
+        [8, _, _, 7] => 22,
+        [8, _, _, 0xE] => 23,
+        [9, _, _, 0] => 24,
+        [0xA, _, _, _] => 25,
+        [0xB, _, _, _] => 26,
+        [0xC, _, _, _] => 27,
+        [0xD, _, _, _] => 28,
+        [0xE, _, 9, 0xE] => 29,
+        [0xE, _, 0xA, 1] => 30,
+        [0xF, 0, 0, 0] => 31,
+        [0xF, _, 0, 1] => 32,
+        [0xF, 0, 0, 2] => 33,
+        [0xF, _, 0, 7] => 34,
+        [0xF, _, 0, 0xA] => 35,
+        [0xF, _, 1, 5] => 36,
+        [0xF, _, 1, 8] => 37,
+        [0xF, _, 1, 0xE] => 38,
+        [0xF, _, 2, 9] => 39,
+        [0xF, _, 3, 0] => 40,
+        [0xF, _, 3, 3] => 41,
+        [0xF, _, 3, 0xA] => 42,
+        [0xF, _, 5, 5] => 43,
+        [0xF, _, 6, 5] => 44,
+        [0xF, _, 7, 5] => 45,
+        [0xF, _, 8, 5] => 46,
+        [_, _, _, _] => OPCODE_FAMILIES.len() - 1,
+    }
+}
+
+// (address, mnemonic) pairs for every 2 byte word of a ROM, as it would be laid out in RAM
+pub fn disassemble_rom(bytes: &[u8]) -> Vec<(u16, String)> {
+    bytes
+        .chunks(2)
+        .enumerate()
+        .map(|(idx, word)| {
+            let addr = START_ADDR + (idx * 2) as u16;
+
+            let mnemonic = match *word {
+                [higher_byte, lower_byte] => {
+                    disassemble(u16::from_be_bytes([higher_byte, lower_byte]))
+                }
+                // Odd trailing byte
+                [byte] => format!("DB {byte:#04X}"),
+                _ => unreachable!("chunks(2) yields 1 or 2 bytes"),
+            };
+
+            (addr, mnemonic)
+        })
+        .collect()
+}
+
+// Bytes of data per DB line in a listing
+const LISTING_DB_BYTES: usize = 8;
+
+// Instructions are 2 bytes, apart from F000 which has its address in the next word
+fn instruction_size(op: u16) -> usize {
+    if op == 0xF000 { 4 } else { 2 }
+}
+
+// Where execution can go in a ROM, found by following every path through it from the start
+struct Flow {
+    // Where each instruction that can be reached begins
+    code: BTreeSet<usize>,
+    // Where jumps, calls and I point
+    targets: BTreeSet<usize>,
+    // Anything a path runs into that can't be right, by the address of the instruction
+    problems: BTreeMap<usize, String>,
+    // Things that look wrong but that games do on purpose, like those for `Report::notes`
+    notes: BTreeMap<usize, String>,
+}
+
+// The 2 bytes at addr as an opcode, if they're both in the ROM
+fn rom_word(bytes: &[u8], addr: usize) -> Option<u16> {
+    let offset = addr.checked_sub(START_ADDR as usize)?;
+
+    Some(u16::from_be_bytes([
+        *bytes.get(offset)?,
+        *bytes.get(offset + 1)?,
+    ]))
+}
+
+fn trace(bytes: &[u8]) -> Flow {
+    let start = START_ADDR as usize;
+    let end = start + bytes.len();
+    let word = |addr| rom_word(bytes, addr);
+
+    let mut flow = Flow {
+        code: BTreeSet::new(),
+        targets: BTreeSet::new(),
+        problems: BTreeMap::new(),
+        notes: BTreeMap::new(),
+    };
+    let mut pending = vec![start];
+
+    while let Some(addr) = pending.pop() {
+        let Some(op) = word(addr) else {
+            continue;
+        };
+
+        if flow.code.contains(&addr) || flow.problems.contains_key(&addr) {
+            continue;
+        }
+
+        // Either a bad instruction, or the path has run into data
+        if opcode_family(op) == OPCODE_FAMILIES.len() - 1 {
+            flow.problems
+                .insert(addr, format!("unknown opcode {op:#06X}"));
+            continue;
+        }
+
+        let size = instruction_size(op);
+
+        if size > 2 && word(addr + 2).is_none() {
+            flow.problems
+                .insert(addr, "LD I, LONG is missing its address".to_string());
+            continue;
+        }
+
+        flow.code.insert(addr);
+
+        let nnn = (op & 0xFFF) as usize;
+        let next = addr + size;
+        // A skip jumps over the next instruction, which might be F000
+        let after_next = next + word(next).map_or(2, instruction_size);
+
+        // Where execution carries on from here, besides a jump or call's target
+        let follows = match op >> 12 {
+            // RET, and BNNN whose target depends on V0
+            _ if op == 0x00EE => vec![],
+            0xB => {
+                flow.targets.insert(nnn);
+                vec![]
+            }
+            0x1 | 0x2 => {
+                let mnemonic = if op >> 12 == 1 { "JP" } else { "CALL" };
+
+                if !(start..end).contains(&nnn) {
+                    flow.problems
+                        .insert(addr, format!("{mnemonic} to {nnn:#05X}, outside the ROM"));
+                } else if !nnn.is_multiple_of(2) {
+                    flow.notes
+                        .insert(addr, format!("{mnemonic} to odd address {nnn:#05X}"));
+                }
+
+                flow.targets.insert(nnn);
+                pending.push(nnn);
+
+                // A call comes back
+                if op >> 12 == 2 { vec![next] } else { vec![] }
+            }
+            0xA => {
+                flow.targets.insert(nnn);
+                vec![next]
+            }
+            0x3 | 0x4 | 0x5 | 0x9 | 0xE => vec![next, after_next],
+            _ => vec![next],
+        };
+
+        for to in follows {
+            if word(to).is_none() {
+                flow.problems
+                    .entry(addr)
+                    .or_insert("runs off the end of the ROM".to_string());
+            }
+
+            pending.push(to);
+        }
+    }
+
+    flow
+}
+
+// What `check` found, by the address of the instruction
+pub struct Report {
+    // Unknown opcodes, jumps and calls outside the ROM, and paths running off the end
+    pub problems: Vec<(u16, String)>,
+    // Jumps and calls to odd addresses. Instructions only have to be 2 byte aligned on the VIP
+    // by convention, and some games pack code around their data like that.
+    pub notes: Vec<(u16, String)>,
+}
+
+// Looks for problems in the code of a ROM without running it, by following every path through
+// it from the start. Data the code never reaches isn't looked at.
+pub fn check(bytes: &[u8]) -> Report {
+    let flow = trace(bytes);
+    let by_address = |found: BTreeMap<usize, String>| {
+        found
+            .into_iter()
+            .map(|(addr, text)| (addr as u16, text))
+            .collect()
+    };
+
+    Report {
+        problems: by_address(flow.problems),
+        notes: by_address(flow.notes),
+    }
+}
+
+// A disassembly of a ROM that reads like source: jump, call and LD I targets get labels like
+// L_0206, which the instructions refer to them by, and only the bytes that execution can be
+// traced to from the start are shown as instructions, with everything else as DB data. It
+// assembles back into the same ROM with `asm::assemble`.
+pub fn listing(bytes: &[u8]) -> String {
+    let start = START_ADDR as usize;
+    let end = start + bytes.len();
+    let word = |addr| rom_word(bytes, addr);
+
+    let Flow {
+        code,
+        targets: mut labels,
+        ..
+    } = trace(bytes);
+
+    // Only addresses in the ROM can have labels to go on
+    labels.retain(|addr| (start..end).contains(addr));
+
+    let label = |addr: usize| format!("L_{addr:04X}");
+
+    // Pass 2: lay it all out in order, with the labels in place of the addresses
+    let mut lines = Vec::new();
+    let mut addr = start;
+
+    while addr < end {
+        if labels.contains(&addr) {
+            lines.push(format!("{}:", label(addr)));
+        }
+
+        let (text, size) = match word(addr) {
+            // An instruction, unless a label or another path through the code lands partway
+            // through it, which there'd be no way to write
+            Some(op)
+                if code.contains(&addr)
+                    && (addr + 1..addr + instruction_size(op))
+                        .all(|inside| !labels.contains(&inside) && !code.contains(&inside)) =>
+            {
+                let nnn = (op & 0xFFF) as usize;
+                let mut text = disassemble(op);
+
+                if op == 0xF000 {
+                    text = format!("{text} {:#06X}", word(addr + 2).unwrap());
+                } else if matches!(op >> 12, 0x1 | 0x2 | 0xA | 0xB) && labels.contains(&nnn) {
+                    text = text.replace(&format!("{nnn:#05X}"), &label(nnn));
+                }
+
+                (text, instruction_size(op))
+            }
+            // Data, up to the next label or instruction
+            _ => {
+                let len = (addr..end)
+                    .take(LISTING_DB_BYTES)
+                    .enumerate()
+                    .find(|&(idx, addr)| {
+                        idx > 0 && (labels.contains(&addr) || code.contains(&addr))
+                    })
+                    .map_or((end - addr).min(LISTING_DB_BYTES), |(idx, _)| idx);
+
+                let data: Vec<_> = bytes[addr - start..addr - start + len]
+                    .iter()
+                    .map(|byte| format!("{byte:#04X}"))
+                    .collect();
+
+                (format!("DB {}", data.join(", ")), len)
+            }
+        };
+
+        lines.push(format!("    {text:<24}; {addr:04X}"));
+        addr += size;
+    }
+
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    fn check_src(src: &str) -> Report {
+        check(&assemble(src).unwrap())
+    }
+
+    #[test]
+    fn disassembles_each_family() {
+        let ops = [
+            (0x0000, "NOP"),
+            (0x00C3, "SCD 3"),
+            (0x00E0, "CLS"),
+            (0x00EE, "RET"),
+            (0x00FB, "SCR"),
+            (0x00FC, "SCL"),
+            (0x00FE, "LOW"),
+            (0x00FF, "HIGH"),
+            (0x1234, "JP 0x234"),
+            (0x2ABC, "CALL 0xABC"),
+            (0x3A02, "SE V10, 0x02"),
+            (0x4B10, "SNE V11, 0x10"),
+            (0x5120, "SE V1, V2"),
+            (0x6A02, "LD V10, 0x02"),
+            (0x7FFF, "ADD V15, 0xFF"),
+            (0x8120, "LD V1, V2"),
+            (0x8121, "OR V1, V2"),
+            (0x8122, "AND V1, V2"),
+            (0x8123, "XOR V1, V2"),
+            (0x8124, "ADD V1, V2"),
+            (0x8125, "SUB V1, V2"),
+            (0x8126, "SHR V1, V2"),
+            (0x8127, "SUBN V1, V2"),
+            (0x812E, "SHL V1, V2"),
+            (0x9120, "SNE V1, V2"),
+            (0xA300, "LD I, 0x300"),
+            (0xB200, "JP V0, 0x200"),
+            (0xC30F, "RND V3, 0x0F"),
+            (0xD01F, "DRW V0, V1, 15"),
+            (0xE49E, "SKP V4"),
+            (0xE4A1, "SKNP V4"),
+            (0xF000, "LD I, LONG"),
+            (0xF201, "PLANE 2"),
+            (0xF002, "AUDIO"),
+            (0xF507, "LD V5, DT"),
+            (0xF50A, "LD V5, K"),
+            (0xF515, "LD DT, V5"),
+            (0xF518, "LD ST, V5"),
+            (0xF51E, "ADD I, V5"),
+            (0xF529, "LD F, V5"),
+            (0xF530, "LD HF, V5"),
+            (0xF533, "LD B, V5"),
+            (0xF53A, "PITCH V5"),
+            (0xF555, "LD [I], V5"),
+            (0xF565, "LD V5, [I]"),
+            (0xF575, "LD R, V5"),
+            (0xF585, "LD V5, R"),
+            (0xFFFF, "DW 0xFFFF"),
+        ];
+
+        for (op, mnemonic) in ops {
+            assert_eq!(disassemble(op), mnemonic, "{op:#06X}");
+        }
+
+        // One of each, in order
+        let families: Vec<_> = ops.iter().map(|&(op, _)| opcode_family(op)).collect();
+        assert_eq!(families, (0..OPCODE_FAMILIES.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn disassembles_roms_from_the_start_address() {
+        assert_eq!(
+            disassemble_rom(&[0x6A, 0x02, 0xD0, 0x1F, 0xF0]),
+            [
+                (0x200, "LD V10, 0x02".to_string()),
+                (0x202, "DRW V0, V1, 15".to_string()),
+                (0x204, "DB 0xF0".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn passes_clean_roms() {
+        let report = check_src("LD V0, 1\nCALL sub\nloop: JP loop\nsub: RET");
+
+        assert!(report.problems.is_empty());
+        assert!(report.notes.is_empty());
+    }
+
+    #[test]
+    fn finds_problems() {
+        let report = check_src("SE V0, 0\nJP 0x300\nDB 0xFF, 0xFF");
+
+        assert_eq!(
+            report.problems,
+            [
+                (0x202, "JP to 0x300, outside the ROM".to_string()),
+                (0x204, "unknown opcode 0xFFFF".to_string()),
+            ]
+        );
+
+        let report = check_src("LD V0, 1");
+
+        assert_eq!(
+            report.problems,
+            [(0x200, "runs off the end of the ROM".to_string())]
+        );
+    }
+
+    #[test]
+    fn lists_code_with_labels() {
+        let src = "
+            LD I, sprite
+            CALL sub
+            loop: JP loop
+            sub: DRW V0, V0, 1
+            RET
+            sprite: DB 0xF0
+        ";
+        let rom = assemble(src).unwrap();
+        let listing = listing(&rom);
+
+        assert_eq!(
+            listing,
+            "    LD I, L_020A            ; 0200
+    CALL L_0206             ; 0202
+L_0204:
+    JP L_0204               ; 0204
+L_0206:
+    DRW V0, V0, 1           ; 0206
+    RET                     ; 0208
+L_020A:
+    DB 0xF0                 ; 020A
+"
+        );
+        assert_eq!(assemble(&listing), Ok(rom));
+    }
+
+    #[test]
+    fn notes_odd_jumps() {
+        // CLS at 0x203, after a padding byte
+        let report = check_src("JP 0x203\nDB 0\nCLS\nJP 0x200");
+
+        assert!(report.problems.is_empty());
+        assert_eq!(
+            report.notes,
+            [(0x200, "JP to odd address 0x203".to_string())]
+        );
+    }
+}
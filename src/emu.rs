@@ -1,59 +1,525 @@
-use rand::random;
-
-use crate::constants::{
-    FONTSET, FONTSET_SIZE, NUM_KEYS, NUM_REGS, RAM_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH, STACK_SIZE,
-    START_ADDR,
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{array, cmp::Reverse, error, fmt, mem};
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+
+use bincode::config;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
+
+#[cfg(feature = "std")]
+use crate::disasm::disassemble;
+use crate::{
+    constants::{
+        AUDIO_PATTERN_SIZE, FONTSET, FONTSET_SIZE, HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH,
+        LARGE_FONTSET, LARGE_FONTSET_ADDR, LARGE_FONTSET_SIZE, NUM_KEYS, NUM_PLANES, NUM_REGS,
+        NUM_RPL_FLAGS, RAM_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH, STACK_SIZE, START_ADDR,
+        TICKS_PER_FRAME,
+    },
+    disasm::{OPCODE_FAMILIES, opcode_family},
+    quirks::Quirks,
+    render::Renderer,
+    rewind::RewindBuffer,
 };
 
+// How much RAM there is and where programs are loaded, for variants that differ from the
+// original 4 KB with programs at 0x200, e.g. XO-CHIP's 64 KB or the ETI 660 loading at 0x600
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmuConfig {
+    pub ram_size: usize,
+    pub start_addr: u16,
+    pub quirks: Quirks,
+}
+
+impl EmuConfig {
+    // XO-CHIP's 64 KB, which F000 NNNN can address all of, and its quirks
+    pub fn xo_chip() -> Self {
+        Self {
+            ram_size: 0x10000,
+            quirks: Quirks::xochip(),
+            ..Default::default()
+        }
+    }
+
+    // The machine a variant ran on, with its own quirks: the original 4 KB for CHIP-8 and
+    // SUPER-CHIP, and 64 KB for XO-CHIP
+    pub fn variant(name: &str) -> Option<Self> {
+        match name {
+            "chip8" => Some(Self::for_quirks(Quirks::chip8())),
+            "schip" => Some(Self::for_quirks(Quirks::schip())),
+            "xochip" => Some(Self::xo_chip()),
+            _ => None,
+        }
+    }
+
+    // The machine a quirks profile runs on: XO-CHIP's 64 KB for its quirks, and the original
+    // 4 KB for everything else
+    pub fn for_quirks(quirks: Quirks) -> Self {
+        if quirks == Quirks::xochip() {
+            Self::xo_chip()
+        } else {
+            Self {
+                quirks,
+                ..Default::default()
+            }
+        }
+    }
+}
+
+impl Default for EmuConfig {
+    fn default() -> Self {
+        Self {
+            ram_size: RAM_SIZE,
+            start_addr: START_ADDR,
+            quirks: Quirks::default(),
+        }
+    }
+}
+
+// A byte of RAM changed by an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RamWrite {
+    pub addr: u16,
+    pub old: u8,
+    pub new: u8,
+}
+
+// A write into code that has already run, which is a sign of self-modifying code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeWrite {
+    // The instruction that made it
+    pub pc: u16,
+    pub write: RamWrite,
+}
+
+// For `set_smc_check`
+#[derive(Debug, Clone, Default)]
+struct SmcCheck {
+    // The instruction being executed
+    pc: u16,
+    // Just past the highest instruction executed so far
+    code_end: usize,
+    // Collected until `take_code_writes`
+    writes: Vec<CodeWrite>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmuError {
+    UnknownOpcode(u16),
+    InvalidSaveState,
+    AddressOutOfRange(u16),
+    #[cfg(feature = "std")]
+    ReadFailed(io::ErrorKind),
+    RomTooLarge {
+        size: usize,
+        max: usize,
+    },
+    PcOutOfBounds(u16),
+    StackOverflow,
+    StackUnderflow,
+    WrongFontsetSize {
+        size: usize,
+        expected: usize,
+    },
+}
+
+impl fmt::Display for EmuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmuError::UnknownOpcode(op) => write!(f, "unknown opcode {op:#06X}"),
+            EmuError::InvalidSaveState => write!(f, "invalid or corrupt save state"),
+            EmuError::AddressOutOfRange(addr) => write!(f, "address {addr:#06X} is outside RAM"),
+            #[cfg(feature = "std")]
+            EmuError::ReadFailed(kind) => write!(f, "unable to read ROM: {kind}"),
+            EmuError::RomTooLarge { size, max } => {
+                write!(f, "ROM is {size} bytes, but only {max} fit in RAM")
+            }
+            EmuError::PcOutOfBounds(pc) => {
+                write!(f, "program counter {pc:#06X} ran off the end of RAM")
+            }
+            EmuError::StackOverflow => write!(f, "stack overflow, too many nested calls"),
+            EmuError::StackUnderflow => write!(f, "stack underflow, return without a call"),
+            EmuError::WrongFontsetSize { size, expected } => {
+                write!(f, "fontset is {size} bytes, but should be {expected}")
+            }
+        }
+    }
+}
+
+impl error::Error for EmuError {}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for EmuError {
+    fn from(err: io::Error) -> Self {
+        EmuError::ReadFailed(err.kind())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Emu {
     pc: u16,
-    ram: [u8; RAM_SIZE],
-    screen: [bool; SCREEN_WIDTH * SCREEN_HEIGHT],
+    ram: Vec<u8>,
+    // Where the program is loaded, and so where the PC starts
+    start_addr: u16,
+    // One bit per pixel, a row of the hi-res display per u128 with the leftmost pixel in the
+    // top bit. In lo-res only the first SCREEN_HEIGHT rows and the top SCREEN_WIDTH bits of
+    // each are used. The XO-CHIP bit planes follow each other, HIRES_SCREEN_HEIGHT rows each.
+    #[serde(with = "BigArray")]
+    screen: [u128; HIRES_SCREEN_HEIGHT * NUM_PLANES],
+    hires: bool,
+    // Bit mask of the planes drawing, clearing and scrolling affect (XO-CHIP)
+    planes: u8,
 
     // Registers
     v_reg: [u8; NUM_REGS],
     i_reg: u16,
 
+    // SCHIP "RPL user flags", named after the HP-48 calculator registers they lived in
+    rpl: [u8; NUM_RPL_FLAGS],
+
     // Stack
     sp: u16,
     stack: [u16; STACK_SIZE],
 
     // Timers
     keys: [bool; NUM_KEYS],
+    // Keys seen held down while waiting in FX0A
+    held_keys: [bool; NUM_KEYS],
     dt: u8,
     st: u8,
+
+    // XO-CHIP sound, played while the sound timer runs. Until a pattern is loaded with F002,
+    // it's a plain beep.
+    audio_pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    pitch: u8,
+
+    // Configuration rather than machine state, so it isn't part of save states
+    #[serde(skip)]
+    quirks: Quirks,
+    #[serde(skip)]
+    ticks_per_frame: usize,
+    #[serde(skip)]
+    idle_skip: bool,
+
+    // Save states of recent frames, when rewinding is on
+    #[serde(skip)]
+    rewind: RewindBuffer,
+
+    // Gets a line per executed instruction when set
+    #[cfg(feature = "std")]
+    #[serde(skip)]
+    trace: Option<Box<dyn Write>>,
+
+    // Source for CXNN. Seeded from the OS unless set with `set_seed`. Without std there's no
+    // OS to ask, so it starts from a fixed seed, and hosts seed it from their own source of
+    // randomness (a hardware RNG, timing of user input, ...).
+    #[serde(skip, default = "default_rng")]
+    rng: StdRng,
+
+    // RAM written by the last instruction
+    #[serde(skip)]
+    ram_writes: Vec<RamWrite>,
+
+    // Instructions executed per OPCODE_FAMILIES entry, when counting
+    #[serde(skip)]
+    coverage: Option<[u64; OPCODE_FAMILIES.len()]>,
+
+    // Times each address was executed, when counting
+    #[serde(skip)]
+    hotspots: Option<BTreeMap<u16, u64>>,
+
+    // Writes below the highest executed code, when checking
+    #[serde(skip)]
+    smc: Option<SmcCheck>,
+
+    // Called with every write an instruction makes to RAM, when set
+    #[serde(skip)]
+    write_hook: Option<Box<dyn FnMut(RamWrite)>>,
 }
 
 impl Default for Emu {
     fn default() -> Self {
+        Self::new_with_config(EmuConfig::default())
+    }
+}
+
+#[cfg(feature = "std")]
+fn default_rng() -> StdRng {
+    StdRng::from_os_rng()
+}
+
+#[cfg(not(feature = "std"))]
+fn default_rng() -> StdRng {
+    StdRng::seed_from_u64(0)
+}
+
+impl Emu {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn new_with_quirks(quirks: Quirks) -> Self {
+        Self::new_with_config(EmuConfig {
+            quirks,
+            ..Default::default()
+        })
+    }
+
+    // RAM can be up to 64 KB, everything 16 bit addresses reach, and has to fit the fonts and
+    // at least some of a program
+    pub fn new_with_config(config: EmuConfig) -> Self {
+        assert!(config.ram_size <= 0x10000, "RAM can't be larger than 64 KB");
+        assert!(
+            (config.start_addr as usize) < config.ram_size
+                && config.ram_size >= LARGE_FONTSET_ADDR + LARGE_FONTSET_SIZE,
+            "RAM has to fit the fonts and start address"
+        );
+
         let mut new_emu = Self {
-            pc: START_ADDR,
-            ram: [0; RAM_SIZE],
-            screen: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            pc: config.start_addr,
+            ram: vec![0; config.ram_size],
+            start_addr: config.start_addr,
+            screen: [0; HIRES_SCREEN_HEIGHT * NUM_PLANES],
+            hires: false,
+            planes: 1,
             // 0-initialize all registers by default
             v_reg: [0; NUM_REGS],
             i_reg: 0,
+            rpl: [0; NUM_RPL_FLAGS],
             sp: 0,
             stack: [0; STACK_SIZE],
             keys: [false; NUM_KEYS],
+            held_keys: [false; NUM_KEYS],
             dt: 0,
             st: 0,
+            audio_pattern: None,
+            pitch: 64,
+            quirks: config.quirks,
+            ticks_per_frame: TICKS_PER_FRAME,
+            idle_skip: false,
+            rewind: RewindBuffer::default(),
+            #[cfg(feature = "std")]
+            trace: None,
+            rng: default_rng(),
+            ram_writes: Vec::new(),
+            coverage: None,
+            hotspots: None,
+            smc: None,
+            write_hook: None,
         };
 
         // Copy built in characters
         new_emu.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        new_emu.ram[LARGE_FONTSET_ADDR..LARGE_FONTSET_ADDR + LARGE_FONTSET_SIZE]
+            .copy_from_slice(&LARGE_FONTSET);
 
         new_emu
     }
-}
 
-impl Emu {
-    pub fn new() -> Self {
-        Default::default()
+    pub fn config(&self) -> EmuConfig {
+        EmuConfig {
+            ram_size: self.ram.len(),
+            start_addr: self.start_addr,
+            quirks: self.quirks,
+        }
+    }
+
+    // Same CXNN results every run for a given seed
+    pub fn new_with_seed(seed: u64) -> Self {
+        let mut new_emu = Self::new();
+        new_emu.set_seed(seed);
+
+        new_emu
+    }
+
+    // Active area of the display, row by row. See `display_size` for its dimensions. A pixel
+    // is lit if it's set in any plane.
+    pub fn get_display(&self) -> Vec<bool> {
+        self.get_display_planes()
+            .into_iter()
+            .map(|pixel| pixel != 0)
+            .collect()
+    }
+
+    // Like `get_display`, but with each pixel's planes as a bit mask: bit 0 for plane 1 and
+    // bit 1 for plane 2, so 0-3 for the 4 XO-CHIP colors
+    pub fn get_display_planes(&self) -> Vec<u8> {
+        let (width, height) = self.display_size();
+
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (y, x)))
+            .map(|(y, x)| {
+                (0..NUM_PLANES)
+                    .filter(|&plane| self.plane(plane)[y] & Self::column_bit(x) != 0)
+                    .fold(0, |pixel, plane| pixel | 1 << plane)
+            })
+            .collect()
+    }
+
+    // (width, height) of the display in the current resolution mode
+    pub fn display_size(&self) -> (usize, usize) {
+        if self.hires {
+            (HIRES_SCREEN_WIDTH, HIRES_SCREEN_HEIGHT)
+        } else {
+            (SCREEN_WIDTH, SCREEN_HEIGHT)
+        }
+    }
+
+    // Hands the active area of the display to a frontend
+    pub fn render(&self, renderer: &mut impl Renderer) {
+        let (width, height) = self.display_size();
+
+        renderer.draw(&self.get_display(), width, height);
+    }
+
+    // Display as RGBA bytes (white on black), row by row. Handy for frontends that blit
+    // straight into a pixel buffer, e.g. an HTML canvas.
+    pub fn get_frame_rgba(&self) -> Vec<u8> {
+        self.get_display()
+            .iter()
+            .flat_map(|&pixel| if pixel { [0xFF; 4] } else { [0, 0, 0, 0xFF] })
+            .collect()
+    }
+
+    // FNV-1a hash of the display and its resolution, stable across runs and Rust versions so
+    // it can be compared against golden values from test ROMs
+    pub fn display_hash(&self) -> u64 {
+        let (width, height) = self.display_size();
+
+        [width as u8, height as u8]
+            .into_iter()
+            .chain(self.get_display_planes())
+            .fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+            })
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn registers(&self) -> &[u8; NUM_REGS] {
+        &self.v_reg
+    }
+
+    pub fn i_register(&self) -> u16 {
+        self.i_reg
+    }
+
+    pub fn stack_pointer(&self) -> u16 {
+        self.sp
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.dt
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.st
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.ram
+    }
+
+    // Everything but RAM as text, for pasting into bug reports or diffing between runs: the
+    // registers in hex, the stack from the bottom up, and the display with # for lit pixels
+    pub fn debug_dump(&self) -> String {
+        let mut lines = vec![format!(
+            "PC {:04X}  I {:04X}  SP {:X}  DT {:02X}  ST {:02X}",
+            self.pc, self.i_reg, self.sp, self.dt, self.st
+        )];
+
+        for (row, regs) in self.v_reg.chunks(8).enumerate() {
+            let regs: Vec<_> = regs
+                .iter()
+                .enumerate()
+                .map(|(x, reg)| format!("V{:X} {reg:02X}", row * 8 + x))
+                .collect();
+            lines.push(regs.join("  "));
+        }
+
+        let stack: Vec<_> = self.stack[..self.sp as usize]
+            .iter()
+            .map(|addr| format!("{addr:04X}"))
+            .collect();
+
+        lines.push(if stack.is_empty() {
+            "Stack empty".to_string()
+        } else {
+            format!("Stack {}", stack.join(" "))
+        });
+
+        let (width, height) = self.display_size();
+        lines.push(format!("Display {width}x{height}"));
+
+        lines.join("\n") + "\n" + &self.display_text()
+    }
+
+    // The display as text, a line per row with # for lit pixels and . for unlit ones
+    pub fn display_text(&self) -> String {
+        let (width, _) = self.display_size();
+
+        self.get_display()
+            .chunks(width)
+            .flat_map(|row| {
+                row.iter()
+                    .map(|&lit| if lit { '#' } else { '.' })
+                    .chain(['\n'])
+            })
+            .collect()
+    }
+
+    pub fn peek(&self, addr: u16) -> Result<u8, EmuError> {
+        self.ram
+            .get(addr as usize)
+            .copied()
+            .ok_or(EmuError::AddressOutOfRange(addr))
+    }
+
+    // Every byte the last instruction wrote to RAM (by FX33 or FX55), in order
+    pub fn last_writes(&self) -> &[RamWrite] {
+        &self.ram_writes
+    }
+
+    pub fn poke(&mut self, addr: u16, byte: u8) -> Result<(), EmuError> {
+        let cell = self
+            .ram
+            .get_mut(addr as usize)
+            .ok_or(EmuError::AddressOutOfRange(addr))?;
+        *cell = byte;
+
+        Ok(())
+    }
+
+    // Moves execution to `addr`, e.g. for a debugger to go back to or skip over an instruction
+    pub fn set_program_counter(&mut self, addr: u16) {
+        self.pc = addr;
+    }
+
+    // The buzzer sounds for as long as the sound timer is nonzero
+    pub fn is_beeping(&self) -> bool {
+        self.st > 0
+    }
+
+    // The XO-CHIP audio pattern, if the program has loaded one
+    pub fn audio_pattern(&self) -> Option<[u8; AUDIO_PATTERN_SIZE]> {
+        self.audio_pattern
     }
 
-    pub fn get_display(&self) -> &[bool] {
-        &self.screen
+    // Samples per second to play the audio pattern at, set from the pitch register. The
+    // default pitch of 64 gives 4000.
+    #[cfg(feature = "std")]
+    pub fn playback_rate(&self) -> f32 {
+        4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
     }
 
     pub fn keypress(&mut self, idx: usize, pressed: bool) {
@@ -62,21 +528,306 @@ impl Emu {
         self.keys[idx] = pressed;
     }
 
-    pub fn load(&mut self, data: &[u8]) {
-        let start = START_ADDR as usize;
-        let end = start + data.len();
+    // Replaces the built in digits FX29 points at, 5 bytes for each of 0-F. The font survives
+    // `reset`.
+    pub fn set_fontset(&mut self, fontset: &[u8]) -> Result<(), EmuError> {
+        if fontset.len() != FONTSET_SIZE {
+            return Err(EmuError::WrongFontsetSize {
+                size: fontset.len(),
+                expected: FONTSET_SIZE,
+            });
+        }
+
+        self.ram[..FONTSET_SIZE].copy_from_slice(fontset);
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, data: &[u8]) -> Result<(), EmuError> {
+        let start = self.start_addr as usize;
+        let max = self.ram.len() - start;
+
+        if data.len() > max {
+            return Err(EmuError::RomTooLarge {
+                size: data.len(),
+                max,
+            });
+        }
+
+        self.ram[start..start + data.len()].copy_from_slice(data);
+
+        // Nothing of a previously loaded, longer program is left behind after this one, and
+        // there's no rewinding back into it
+        self.ram[start + data.len()..].fill(0);
+        self.rewind.clear();
+
+        Ok(())
+    }
+
+    // Like `load`, for ROMs coming from a file, stdin, a socket, ...
+    #[cfg(feature = "std")]
+    pub fn load_from<R: Read>(&mut self, mut reader: R) -> Result<(), EmuError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        self.load(&data)
+    }
+
+    // Adds RAM, up to `ram_size` in all, e.g. for switching to XO-CHIP's quirks while running.
+    // It never shrinks, so nothing a running program has put in RAM is lost. Rewind history
+    // is dropped if it grows, as states saved with the old RAM size can't be loaded any more.
+    pub fn grow_ram(&mut self, ram_size: usize) {
+        assert!(ram_size <= 0x10000, "RAM can't be larger than 64 KB");
+
+        if ram_size > self.ram.len() {
+            self.ram.resize(ram_size, 0);
+            self.rewind.clear();
+        }
+    }
+
+    // Restart the machine with the currently loaded program still in RAM. RPL flags are kept
+    // too, as they're meant to survive restarts, but not the rewind history of the last run.
+    pub fn reset(&mut self) {
+        let config = self.config();
+        let ram = mem::take(&mut self.ram);
+
+        *self = Self {
+            rpl: self.rpl,
+            ticks_per_frame: self.ticks_per_frame,
+            idle_skip: self.idle_skip,
+            rewind: RewindBuffer::with_depth(self.rewind.depth()),
+            #[cfg(feature = "std")]
+            trace: self.trace.take(),
+            rng: self.rng.clone(),
+            coverage: self.coverage,
+            hotspots: self.hotspots.take(),
+            smc: self.smc.take(),
+            write_hook: self.write_hook.take(),
+            ..Self::new_with_config(config)
+        };
+
+        // The font is kept too, in case it was replaced with `set_fontset`
+        let start = self.start_addr as usize;
+        self.ram[..FONTSET_SIZE].copy_from_slice(&ram[..FONTSET_SIZE]);
+        self.ram[start..].copy_from_slice(&ram[start..]);
+    }
+
+    // Snapshot of the full machine state in a compact binary format
+    pub fn save_state(&self) -> Vec<u8> {
+        bincode::serde::encode_to_vec(self, config::standard())
+            .expect("serializing to a Vec can't fail")
+    }
+
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), EmuError> {
+        let (loaded, _): (Emu, _) = bincode::serde::decode_from_slice(data, config::standard())
+            .map_err(|_| EmuError::InvalidSaveState)?;
+
+        // A state can come from anywhere, e.g. a file saved with another RAM size or edited by
+        // hand. Anything out of range here would panic later on rather than fail now.
+        let ram_size = self.ram.len();
+
+        if loaded.ram.len() != ram_size
+            || loaded.sp as usize > STACK_SIZE
+            || loaded.pc as usize >= ram_size
+            || loaded.start_addr as usize >= ram_size
+        {
+            return Err(EmuError::InvalidSaveState);
+        }
+
+        *self = Self {
+            quirks: self.quirks,
+            ticks_per_frame: self.ticks_per_frame,
+            idle_skip: self.idle_skip,
+            rewind: mem::take(&mut self.rewind),
+            #[cfg(feature = "std")]
+            trace: self.trace.take(),
+            rng: self.rng.clone(),
+            coverage: self.coverage,
+            hotspots: self.hotspots.take(),
+            smc: self.smc.take(),
+            write_hook: self.write_hook.take(),
+            ..loaded
+        };
+
+        Ok(())
+    }
+
+    // Log every executed instruction (address, opcode, disassembly and the registers after
+    // it ran) to the given sink
+    #[cfg(feature = "std")]
+    pub fn set_trace(&mut self, sink: Box<dyn Write>) {
+        self.trace = Some(sink);
+    }
+
+    // Have every write an instruction makes to RAM (by FX33 or FX55) passed to the hook as it
+    // happens, e.g. to log them or watch for particular addresses
+    pub fn set_write_hook(&mut self, hook: Box<dyn FnMut(RamWrite)>) {
+        self.write_hook = Some(hook);
+    }
+
+    // Count executed instructions by family, for `coverage_report`. Off by default, so it
+    // costs nothing unless asked for. Turning it on again starts the counts over.
+    pub fn set_coverage(&mut self, enabled: bool) {
+        self.coverage = enabled.then_some([0; OPCODE_FAMILIES.len()]);
+    }
+
+    // (family, count) for every instruction family executed since `set_coverage`, e.g.
+    // ("8XY4 ADD", 12). Unrecognized opcodes are counted under "unknown".
+    pub fn coverage_report(&self) -> Vec<(&'static str, u64)> {
+        let Some(coverage) = &self.coverage else {
+            return Vec::new();
+        };
+
+        OPCODE_FAMILIES
+            .iter()
+            .zip(coverage)
+            .filter(|&(_, &count)| count > 0)
+            .map(|(&family, &count)| (family, count))
+            .collect()
+    }
+
+    // Count how many times each address is executed, for `hotspots`. Off by default like
+    // `set_coverage`.
+    pub fn set_hotspots(&mut self, enabled: bool) {
+        self.hotspots = enabled.then(BTreeMap::new);
+    }
+
+    // The n most executed addresses since `set_hotspots`, with how many times each ran
+    pub fn hotspots(&self, n: usize) -> Vec<(u16, u64)> {
+        let Some(hotspots) = &self.hotspots else {
+            return Vec::new();
+        };
+
+        let mut hotspots: Vec<_> = hotspots.iter().map(|(&pc, &count)| (pc, count)).collect();
+        hotspots.sort_unstable_by_key(|&(pc, count)| (Reverse(count), pc));
+        hotspots.truncate(n);
+
+        hotspots
+    }
+
+    // Collect writes into code that has already run, i.e. anywhere below the highest address
+    // executed so far, for `take_code_writes`. Off by default like `set_coverage`.
+    pub fn set_smc_check(&mut self, enabled: bool) {
+        self.smc = enabled.then(SmcCheck::default);
+    }
 
-        self.ram[start..end].copy_from_slice(data);
+    // Writes into code since the last call, oldest first
+    pub fn take_code_writes(&mut self) -> Vec<CodeWrite> {
+        self.smc
+            .as_mut()
+            .map(|smc| mem::take(&mut smc.writes))
+            .unwrap_or_default()
     }
 
-    pub fn tick(&mut self) {
+    pub fn tick(&mut self) -> Result<(), EmuError> {
+        let pc = self.pc;
+        self.ram_writes.clear();
+
+        if let Some(hotspots) = &mut self.hotspots {
+            *hotspots.entry(pc).or_default() += 1;
+        }
+
+        if let Some(smc) = &mut self.smc {
+            smc.pc = pc;
+            smc.code_end = smc.code_end.max(pc as usize + 2);
+        }
+
         // Fetch
-        let op = self.fetch();
+        let op = self.fetch()?;
 
         // Decode
 
         // Execute
-        self.execute(op);
+        self.execute(op)?;
+
+        #[cfg(feature = "std")]
+        if let Some(trace) = &mut self.trace {
+            let regs: Vec<_> = self.v_reg.iter().map(|reg| format!("{reg:02X}")).collect();
+
+            // Tracing is best effort, a failing sink shouldn't stop emulation
+            let _ = writeln!(
+                trace,
+                "{pc:04X}  {op:04X}  {:<16}  V: {}  I: {:04X}",
+                disassemble(op),
+                regs.join(" "),
+                self.i_reg
+            );
+        }
+
+        Ok(())
+    }
+
+    // One 60Hz frame worth of emulation. Lets an external clock (requestAnimationFrame, a GUI
+    // timer, ...) drive the emulator instead of owning a blocking loop.
+    pub fn run_frame(&mut self) -> Result<(), EmuError> {
+        for _ in 0..self.ticks_per_frame {
+            let pc = self.pc;
+
+            self.tick()?;
+
+            if self.idle_skip && self.is_idle_at(pc) {
+                break;
+            }
+        }
+
+        self.tick_timers();
+
+        if self.rewind.depth() > 0 {
+            let snapshot = self.save_state();
+            self.rewind.push(snapshot);
+        }
+
+        Ok(())
+    }
+
+    // Runs frames back to back with no clock at all, for headless runs like tests
+    pub fn run_frames(&mut self, n: usize) -> Result<(), EmuError> {
+        for _ in 0..n {
+            self.run_frame()?;
+        }
+
+        Ok(())
+    }
+
+    // Instructions executed per `run_frame`, i.e. the CPU clock speed. Defaults to
+    // TICKS_PER_FRAME.
+    pub fn set_ticks_per_frame(&mut self, ticks: usize) {
+        self.ticks_per_frame = ticks;
+    }
+
+    pub fn ticks_per_frame(&self) -> usize {
+        self.ticks_per_frame
+    }
+
+    // Cut a frame short once the program is just waiting on a timer or key, which can only
+    // change between frames. The rest of the frame's instructions would do nothing anyway.
+    pub fn set_idle_skip(&mut self, idle_skip: bool) {
+        self.idle_skip = idle_skip;
+    }
+
+    // Takes effect from the next instruction, e.g. once a ROM has been identified
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    // Restart the CXNN random sequence from a fixed seed
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    // How many frames `rewind` can go back. Off (0) by default, as it takes a save state every
+    // frame, which isn't worth it unless something is going to rewind.
+    pub fn set_rewind_depth(&mut self, depth: usize) {
+        self.rewind = RewindBuffer::with_depth(depth);
+    }
+
+    // Step back to the most recently captured frame. Returns false once history runs out, or
+    // if the snapshot can't be loaded.
+    pub fn rewind(&mut self) -> bool {
+        match self.rewind.pop() {
+            Some(snapshot) => self.load_state(&snapshot).is_ok(),
+            None => false,
+        }
     }
 
     pub fn tick_timers(&mut self) {
@@ -84,52 +835,53 @@ impl Emu {
             self.dt -= 1;
         }
 
-        if self.st == 1 {
-            // BEEP
-            todo!();
-        }
-
+        // The frontend is responsible for actually beeping, see `is_beeping`
         if self.st > 0 {
             self.st -= 1;
         }
     }
 
-    fn fetch(&mut self) -> u16 {
-        debug_assert!(
-            (self.pc as usize) < RAM_SIZE - 1,
-            "program counter out of bounds!"
-        );
+    fn fetch(&mut self) -> Result<u16, EmuError> {
+        // The PC can end up at (or past) the last byte of RAM, e.g. from a skip at the top of
+        // memory. That's an error unless the wrap_pc quirk wraps both bytes back around.
+        let ram_size = self.ram.len();
+
+        let pc = if self.quirks.wrap_pc {
+            self.pc as usize % ram_size
+        } else if (self.pc as usize) < ram_size - 1 {
+            self.pc as usize
+        } else {
+            return Err(EmuError::PcOutOfBounds(self.pc));
+        };
 
         // Assume big endian order, so lower address = high byte, higher address = low byte
         // Each byte is a u8 but the higher byte needs to be left shifted into place in a u16
-        let higher_byte = self.ram[self.pc as usize];
-        let lower_byte = self.ram[(self.pc + 1) as usize];
+        let higher_byte = self.ram[pc];
+        let lower_byte = self.ram[(pc + 1) % ram_size];
 
         let op = u16::from_be_bytes([higher_byte, lower_byte]);
-        self.pc += 2;
+        self.pc = if self.quirks.wrap_pc {
+            ((pc + 2) % ram_size) as u16
+        } else {
+            (pc + 2) as u16
+        };
 
-        op
+        Ok(op)
     }
 
-    fn execute(&mut self, op: u16) {
+    fn execute(&mut self, op: u16) -> Result<(), EmuError> {
+        if let Some(coverage) = &mut self.coverage {
+            coverage[opcode_family(op)] += 1;
+        }
+
         // Split 2 byte operation into 4 nibbles (4 bits each).
         // &-with 0xF to remove extraneous data
         let nibbles = [op >> 12, op >> 8, op >> 4, op].map(|nibble| (nibble & 0xF) as u8);
 
+        // Decoded by the first nibble, with the families that have several instructions
+        // decoded the rest of the way in methods of their own
         match nibbles {
-            // NOP - Nothing
-            [0, 0, 0, 0] => return,
-
-            // CLS - Clear screen
-            [0, 0, 0xE, 0] => {
-                self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
-            }
-
-            // RET - Return from subroutine
-            [0, 0, 0xE, 0xE] => {
-                let ret_addr = self.pop();
-                self.pc = ret_addr;
-            }
+            [0, ..] => return self.execute_0(op, nibbles),
 
             // 1NNN - Jump
             [1, _, _, _] => {
@@ -141,7 +893,7 @@ impl Emu {
             [2, _, _, _] => {
                 let nnn = op & 0xFFF;
 
-                self.push(self.pc); // So we can return later
+                self.push(self.pc)?; // So we can return later
                 self.pc = nnn;
             }
 
@@ -151,7 +903,7 @@ impl Emu {
                 let nn = (op & 0xFF) as u8;
 
                 if self.v_reg[x as usize] == nn {
-                    self.pc += 2;
+                    self.skip_next();
                 }
             }
 
@@ -161,7 +913,7 @@ impl Emu {
                 let nn = (op & 0xFF) as u8;
 
                 if self.v_reg[x as usize] != nn {
-                    self.pc += 2;
+                    self.skip_next();
                 }
             }
 
@@ -171,7 +923,7 @@ impl Emu {
                 let y = nibbles[2];
 
                 if self.v_reg[x as usize] == self.v_reg[y as usize] {
-                    self.pc += 2;
+                    self.skip_next();
                 }
             }
 
@@ -191,36 +943,230 @@ impl Emu {
                 self.v_reg[x as usize] = self.v_reg[x as usize].wrapping_add(nn);
             }
 
-            // 8XY0 - VX = VY
-            [8, _, _, 0] => {
+            [8, ..] => return self.execute_8(op, nibbles),
+
+            // 9XY0 - Skip next if VX != VY
+            [9, _, _, 0] => {
                 let x = nibbles[1];
                 let y = nibbles[2];
 
-                self.v_reg[x as usize] = self.v_reg[y as usize];
+                if self.v_reg[x as usize] != self.v_reg[y as usize] {
+                    self.skip_next();
+                }
             }
 
-            // 8XY1 - VX |= VY
-            [8, _, _, 1] => {
-                let x = nibbles[1];
-                let y = nibbles[2];
+            // ANNN - I = NNN
+            [0xA, _, _, _] => {
+                let nnn = op & 0xFFF;
+                self.i_reg = nnn;
+            }
 
-                self.v_reg[x as usize] |= self.v_reg[y as usize];
+            // BNNN - Jump to V0 + NNN
+            // With the jump_with_vx quirk this is BXNN instead - jump to VX + NN
+            [0xB, _, _, _] => {
+                if self.quirks.jump_with_vx {
+                    let x = nibbles[1];
+                    let nn = op & 0xFF;
+                    self.pc = (self.v_reg[x as usize] as u16) + nn;
+                } else {
+                    let nnn = op & 0xFFF;
+                    self.pc = (self.v_reg[0] as u16) + nnn;
+                }
             }
 
-            // 8XY2 - VX &= VY
-            [8, _, _, 2] => {
+            // CXNN - VX = rand() & NN
+            [0xC, _, _, _] => {
                 let x = nibbles[1];
-                let y = nibbles[2];
+                let nn = (op & 0xFF) as u8;
 
-                self.v_reg[x as usize] &= self.v_reg[y as usize];
+                let rng: u8 = self.rng.random();
+
+                self.v_reg[x as usize] = rng & nn;
             }
 
-            // 8XY3 - VX ^= VY
-            [8, _, _, 3] => {
+            // DXYN - Draw sprite
+            // Draw an n byte sprite stored at I to (VX, VY). The starting position always
+            // wraps around the screen. Pixels running off the edges wrap around too, or get
+            // clipped with the wrap_x/wrap_y quirks off. VF is cleared up front and set to 1 if
+            // any lit pixel gets turned off (a collision).
+            // In hi-res mode, DXY0 draws a 16x16 sprite stored as two bytes per row (SCHIP).
+            // The sprite is drawn into each selected plane, with the data for plane 2 right
+            // after plane 1's when both are (XO-CHIP).
+            [0xD, _, _, _] => {
+                let x = nibbles[1];
+                let y = nibbles[2];
+                let n = nibbles[3]; // Sprites can have a height of 1 - 15.
+
+                let (sprite_width, sprite_height) = if n == 0 && self.hires {
+                    (16, 16)
+                } else {
+                    (8, n)
+                };
+
+                let (width, height) = self.display_size();
+
+                let x_coord = self.v_reg[x as usize] as usize % width;
+                let y_coord = self.v_reg[y as usize] as usize % height;
+
+                // Coordinates are read first, since VX/VY could be VF itself
+                self.v_reg[0xF] = 0;
+
+                let row_mask = self.row_mask();
+                let sprite_size = sprite_height as usize * sprite_width / 8;
+                let mut sprite_addr = self.i_reg as usize;
+
+                for plane in self.selected_planes() {
+                    for row in 0..sprite_height {
+                        let mut screen_y = y_coord + row as usize;
+
+                        if screen_y >= height {
+                            if !self.quirks.wrap_y {
+                                break;
+                            }
+
+                            screen_y %= height;
+                        }
+
+                        let sprite_pixel_row = if sprite_width == 16 {
+                            let address = sprite_addr + row as usize * 2;
+                            u16::from_be_bytes([self.read_ram(address), self.read_ram(address + 1)])
+                        } else {
+                            self.read_ram(sprite_addr + row as usize) as u16
+                        };
+
+                        // Line the sprite row up with the left edge, then move it over to X.
+                        // Anything past the right edge gets masked off, or brought back around on
+                        // the left when wrapping.
+                        let aligned =
+                            (sprite_pixel_row as u128) << (HIRES_SCREEN_WIDTH - sprite_width);
+                        let mut sprite_bits = aligned >> x_coord;
+
+                        if self.quirks.wrap_x {
+                            sprite_bits |=
+                                aligned.checked_shl((width - x_coord) as u32).unwrap_or(0);
+                        }
+
+                        sprite_bits &= row_mask;
+
+                        // Each sprite pixel is going to be XOR'd with the existing
+                        // display pixel:
+                        // SP  DP
+                        // ON  OFF -> ON
+                        // ON  ON  -> OFF
+                        // OFF ON  -> ON
+                        // OFF OFF -> OFF
+                        let screen_row = &mut self.plane_mut(plane)[screen_y];
+                        let collided = *screen_row & sprite_bits != 0;
+                        *screen_row ^= sprite_bits;
+
+                        if collided {
+                            self.v_reg[0xF] = 1;
+                        }
+                    }
+
+                    sprite_addr += sprite_size;
+                }
+            }
+
+            [0xE, ..] => return self.execute_e(op, nibbles),
+
+            [0xF, ..] => return self.execute_f(op, nibbles),
+
+            [_, _, _, _] => return Err(EmuError::UnknownOpcode(op)),
+        }
+
+        Ok(())
+    }
+
+    // 00__ - Clearing the screen, returning, and the SCHIP screen instructions
+    fn execute_0(&mut self, op: u16, nibbles: [u8; 4]) -> Result<(), EmuError> {
+        match nibbles {
+            // NOP - Nothing
+            [0, 0, 0, 0] => (),
+
+            // CLS - Clear screen
+            // Only the selected planes are cleared (XO-CHIP)
+            [0, 0, 0xE, 0] => {
+                self.clear_planes(self.planes);
+            }
+
+            // RET - Return from subroutine
+            [0, 0, 0xE, 0xE] => {
+                let ret_addr = self.pop()?;
+                self.pc = ret_addr;
+            }
+
+            // 00CN - Scroll down N lines (SCHIP)
+            [0, 0, 0xC, n] => {
+                self.scroll_down(n as usize);
+            }
+
+            // 00FB - Scroll right 4 pixels (SCHIP)
+            [0, 0, 0xF, 0xB] => {
+                let amount = self.horizontal_scroll_amount();
+                self.scroll_right(amount);
+            }
+
+            // 00FC - Scroll left 4 pixels (SCHIP)
+            [0, 0, 0xF, 0xC] => {
+                let amount = self.horizontal_scroll_amount();
+                self.scroll_left(amount);
+            }
+
+            // 00FE - Disable hi-res mode (SCHIP)
+            [0, 0, 0xF, 0xE] => {
+                self.hires = false;
+                self.clear_planes(!0);
+            }
+
+            // 00FF - Enable hi-res mode (SCHIP)
+            [0, 0, 0xF, 0xF] => {
+                self.hires = true;
+                self.clear_planes(!0);
+            }
+
+            [_, _, _, _] => return Err(EmuError::UnknownOpcode(op)),
+        }
+
+        Ok(())
+    }
+
+    // 8XY_ - Arithmetic and logic between two registers
+    fn execute_8(&mut self, op: u16, nibbles: [u8; 4]) -> Result<(), EmuError> {
+        match nibbles {
+            // 8XY0 - VX = VY
+            [8, _, _, 0] => {
+                let x = nibbles[1];
+                let y = nibbles[2];
+
+                self.v_reg[x as usize] = self.v_reg[y as usize];
+            }
+
+            // 8XY1 - VX |= VY
+            [8, _, _, 1] => {
+                let x = nibbles[1];
+                let y = nibbles[2];
+
+                self.v_reg[x as usize] |= self.v_reg[y as usize];
+                self.logic_vf_reset();
+            }
+
+            // 8XY2 - VX &= VY
+            [8, _, _, 2] => {
+                let x = nibbles[1];
+                let y = nibbles[2];
+
+                self.v_reg[x as usize] &= self.v_reg[y as usize];
+                self.logic_vf_reset();
+            }
+
+            // 8XY3 - VX ^= VY
+            [8, _, _, 3] => {
                 let x = nibbles[1];
                 let y = nibbles[2];
 
                 self.v_reg[x as usize] ^= self.v_reg[y as usize];
+                self.logic_vf_reset();
             }
 
             // 8XY4 - VX += VY
@@ -254,10 +1200,16 @@ impl Emu {
             }
 
             // 8XY6 - VX >>= 1
-            // Assuming the Cowgod specification, VY is ignored here
-            // LSB of original VX is stored in VF.
+            // Following the Cowgod specification VY is ignored here, unless the
+            // shift_uses_vy quirk is set, in which case VX = VY >> 1.
+            // LSB of the shifted value is stored in VF.
             [8, _, _, 6] => {
                 let x = nibbles[1];
+                let y = nibbles[2];
+
+                if self.quirks.shift_uses_vy {
+                    self.v_reg[x as usize] = self.v_reg[y as usize];
+                }
 
                 let lsb = self.v_reg[x as usize] & 1;
 
@@ -281,10 +1233,16 @@ impl Emu {
             }
 
             // 8XYE - VX <<= 1
-            // Assuming the Cowgod specification, VY is ignored here
-            // MSB of original VX is stored in VF.
+            // Following the Cowgod specification VY is ignored here, unless the
+            // shift_uses_vy quirk is set, in which case VX = VY << 1.
+            // MSB of the shifted value is stored in VF.
             [8, _, _, 0xE] => {
                 let x = nibbles[1];
+                let y = nibbles[2];
+
+                if self.quirks.shift_uses_vy {
+                    self.v_reg[x as usize] = self.v_reg[y as usize];
+                }
 
                 // Mask isn't necessary but good to ensure we only get one bit
                 let msb = (self.v_reg[x as usize] >> 7) & 1;
@@ -293,89 +1251,15 @@ impl Emu {
                 self.v_reg[0xF] = msb;
             }
 
-            // 9XY0 - Skip next if VX != VY
-            [9, _, _, 0] => {
-                let x = nibbles[1];
-                let y = nibbles[2];
-
-                if self.v_reg[x as usize] != self.v_reg[y as usize] {
-                    self.pc += 2;
-                }
-            }
-
-            // ANNN - I = NNN
-            [0xA, _, _, _] => {
-                let nnn = op & 0xFFF;
-                self.i_reg = nnn;
-            }
-
-            // BNNN - Jump to V0 + NNN
-            [0xB, _, _, _] => {
-                let nnn = op & 0xFFF;
-                self.pc = (self.v_reg[0] as u16) + nnn;
-            }
-
-            // CXNN - VX = rand() & NN
-            [0xC, _, _, _] => {
-                let x = nibbles[1];
-                let nn = (op & 0xFF) as u8;
-
-                let rng: u8 = random();
-
-                self.v_reg[x as usize] = rng & nn;
-            }
-
-            // DXYN - Draw sprite
-            // Draw a sprite starting horizontally at VI to VI + n. Wrap sprites around
-            // if necessary. If any pixel is unset, set VF to 1 (or 0 if the opposite
-            // occurs.
-            [0xD, _, _, _] => {
-                let x = nibbles[1];
-                let y = nibbles[2];
-                let n = nibbles[3]; // Sprites can have a height of 1 - 15.
-
-                let x_coord = self.v_reg[x as usize];
-                let y_coord = self.v_reg[y as usize];
-
-                let mut pixel_unset = false;
-
-                for row in 0..n {
-                    let address = self.i_reg + (row as u16);
-                    let sprite_pixel_row = self.ram[address as usize];
-
-                    for col in 0..8 {
-                        // Iterate from MSB to LSB for left to right drawing
-                        let sprite_pixel = (sprite_pixel_row >> (7 - col)) & 1;
-
-                        if sprite_pixel == 1 {
-                            // Wrapping add, as a register could hold the maximum u8 value and
-                            // would need to be wrapped around
-                            let screen_x = x_coord.wrapping_add(col) as usize % SCREEN_WIDTH;
-                            let screen_y = y_coord.wrapping_add(row) as usize % SCREEN_HEIGHT;
-
-                            let screen_idx = SCREEN_WIDTH * screen_y + screen_x;
-
-                            debug_assert!(
-                                screen_idx < SCREEN_WIDTH * SCREEN_HEIGHT,
-                                "incorrectly calculated screen index when drawing!"
-                            );
-
-                            // Each sprite pixel is going to be XOR'd with the existing
-                            // display pixel:
-                            // SP  DP
-                            // ON  OFF -> ON
-                            // ON  ON  -> OFF
-                            // OFF ON  -> ON
-                            // OFF OFF -> OFF
-                            pixel_unset |= self.screen[screen_idx];
-                            self.screen[screen_idx] ^= true;
-                        }
-                    }
-                }
+            [_, _, _, _] => return Err(EmuError::UnknownOpcode(op)),
+        }
 
-                self.v_reg[0xF] = pixel_unset as u8;
-            }
+        Ok(())
+    }
 
+    // EX9E/EXA1 - Skips on the keypad
+    fn execute_e(&mut self, op: u16, nibbles: [u8; 4]) -> Result<(), EmuError> {
+        match nibbles {
             // EX9E - Skip if key pressed
             [0xE, _, 9, 0xE] => {
                 let x = nibbles[1];
@@ -384,7 +1268,7 @@ impl Emu {
                 let key = self.keys[vx as usize];
 
                 if key {
-                    self.pc += 2;
+                    self.skip_next();
                 }
             }
 
@@ -396,28 +1280,73 @@ impl Emu {
                 let key = self.keys[vx as usize];
 
                 if !key {
-                    self.pc += 2;
+                    self.skip_next();
                 }
             }
 
+            [_, _, _, _] => return Err(EmuError::UnknownOpcode(op)),
+        }
+
+        Ok(())
+    }
+
+    // FX__ - Timers, I, the keypad wait, memory and everything else
+    fn execute_f(&mut self, op: u16, nibbles: [u8; 4]) -> Result<(), EmuError> {
+        match nibbles {
+            // F000 NNNN - Set I to the 16 bit address in the next two bytes (XO-CHIP)
+            [0xF, 0, 0, 0] => {
+                let pc = self.pc as usize;
+
+                self.i_reg = u16::from_be_bytes([self.read_ram(pc), self.read_ram(pc + 1)]);
+                self.pc = self.pc.wrapping_add(2);
+            }
+
+            // FN01 - Select the planes (a bit mask, 0-3) to draw, clear and scroll (XO-CHIP)
+            [0xF, n, 0, 1] => {
+                self.planes = n & 0b11;
+            }
+
+            // F002 - Load the 16 byte audio pattern at I (XO-CHIP)
+            [0xF, 0, 0, 2] => {
+                let i = self.i_reg as usize;
+
+                self.audio_pattern = Some(array::from_fn(|idx| self.read_ram(i + idx)));
+            }
+
             // FX07 - VX = DT
             [0xF, _, 0, 7] => {
                 let x = nibbles[1];
                 self.v_reg[x as usize] = self.dt;
             }
 
-            // FX0A - Wait until key pressed
-            // If a key isn't pressed, this moves the program counter back to rerun the
+            // FX0A - Wait until key released
+            // Like the original interpreter, a key only counts once it's been pressed and
+            // then let go. With the key_wait_on_press quirk, any held key counts straight
+            // away instead.
+            // If there's no key yet, this moves the program counter back to rerun the
             // instruction. Better than a loop so we can still take input.
             [0xF, _, 0, 0xA] => {
                 let x = nibbles[1];
 
-                match self.keys.iter().position(|&key| key) {
+                let key = if self.quirks.key_wait_on_press {
+                    self.keys.iter().position(|&key| key)
+                } else {
+                    for (held, &pressed) in self.held_keys.iter_mut().zip(&self.keys) {
+                        *held |= pressed;
+                    }
+
+                    (0..NUM_KEYS).find(|&key| self.held_keys[key] && !self.keys[key])
+                };
+
+                match key {
                     Some(position) => {
                         self.v_reg[x as usize] = position as u8;
+                        self.held_keys = [false; NUM_KEYS];
                     }
+                    // Back to this instruction, which with the wrap_pc quirk could be at the
+                    // top of RAM with the PC already wrapped around to 0
                     None => {
-                        self.pc -= 2;
+                        self.pc = ((self.pc as usize + self.ram.len() - 2) % self.ram.len()) as u16;
                     }
                 }
             }
@@ -453,6 +1382,16 @@ impl Emu {
                                             // starting at address 0.
             }
 
+            // FX30 - Set I to large font address (SCHIP)
+            [0xF, _, 3, 0] => {
+                let x = nibbles[1];
+
+                let vx = self.v_reg[x as usize];
+
+                // Each large digit is 10 bytes, stored after the small font
+                self.i_reg = LARGE_FONTSET_ADDR as u16 + vx as u16 * 10;
+            }
+
             // FX33 - I = BCD of VX
             // Take VX, which is at most a 3 digit number and store each individual
             // digit in the I register.
@@ -465,50 +1404,1622 @@ impl Emu {
                 let tens = (vx / 10) % 10;
                 let hundreds = vx / 100;
 
-                self.ram[self.i_reg as usize] = hundreds;
-                self.ram[(self.i_reg + 1) as usize] = tens;
-                self.ram[(self.i_reg + 2) as usize] = ones;
+                let i = self.i_reg as usize;
+
+                self.write_ram(i, hundreds);
+                self.write_ram(i + 1, tens);
+                self.write_ram(i + 2, ones);
+            }
+
+            // FX3A - Set the audio pitch to VX (XO-CHIP)
+            [0xF, _, 3, 0xA] => {
+                let x = nibbles[1];
+                self.pitch = self.v_reg[x as usize];
             }
 
             // FX55 - Store V0 through VX into I
+            // With the increment_i_on_store_load quirk, I ends up at I + X + 1
             [0xF, _, 5, 5] => {
                 let x = nibbles[1] as usize;
 
                 let i = self.i_reg as usize;
 
                 for idx in 0..=x {
-                    self.ram[i + idx] = self.v_reg[idx];
+                    self.write_ram(i + idx, self.v_reg[idx]);
+                }
+
+                if self.quirks.increment_i_on_store_load {
+                    self.i_reg = self.i_reg.wrapping_add((x + 1) as u16);
                 }
             }
 
             // FX65 - Store I into V0 through VX
+            // With the increment_i_on_store_load quirk, I ends up at I + X + 1
             [0xF, _, 6, 5] => {
                 let x = nibbles[1] as usize;
 
                 let i = self.i_reg as usize;
 
                 for idx in 0..=x {
-                    self.v_reg[idx] = self.ram[i + idx];
+                    self.v_reg[idx] = self.read_ram(i + idx);
+                }
+
+                if self.quirks.increment_i_on_store_load {
+                    self.i_reg = self.i_reg.wrapping_add((x + 1) as u16);
                 }
             }
 
-            [_, _, _, _] => unimplemented!("Unimplemented opcode: {op}"),
+            // FX75 - Store V0 through VX into the RPL flags (SCHIP)
+            // There are only 8 flags, so X is capped at 7
+            [0xF, _, 7, 5] => {
+                let x = (nibbles[1] as usize).min(NUM_RPL_FLAGS - 1);
+
+                self.rpl[..=x].copy_from_slice(&self.v_reg[..=x]);
+            }
+
+            // FX85 - Store the RPL flags into V0 through VX (SCHIP)
+            [0xF, _, 8, 5] => {
+                let x = (nibbles[1] as usize).min(NUM_RPL_FLAGS - 1);
+
+                self.v_reg[..=x].copy_from_slice(&self.rpl[..=x]);
+            }
+
+            [_, _, _, _] => return Err(EmuError::UnknownOpcode(op)),
+        }
+
+        Ok(())
+    }
+
+    // Whether the instruction at pc just ran without getting anywhere, i.e. a jump to itself or
+    // an FX0A still waiting for a key
+    fn is_idle_at(&self, pc: u16) -> bool {
+        if self.pc != pc {
+            return false;
+        }
+
+        let op = u16::from_be_bytes([self.read_ram(pc as usize), self.read_ram(pc as usize + 1)]);
+
+        op & 0xF000 == 0x1000 || op & 0xF0FF == 0xF00A
+    }
+
+    // Skips go over the whole next instruction, which is 4 bytes for F000 NNNN
+    fn skip_next(&mut self) {
+        let pc = self.pc as usize;
+        let next_op = u16::from_be_bytes([self.read_ram(pc), self.read_ram(pc + 1)]);
+
+        let len = if next_op == 0xF000 { 4 } else { 2 };
+        self.pc = self.pc.wrapping_add(len);
+    }
+
+    // Instructions read and write RAM through these. Addresses past the end wrap back around,
+    // rather than I near the top of memory reading or writing off the end of it.
+    fn read_ram(&self, addr: usize) -> u8 {
+        self.ram[addr % self.ram.len()]
+    }
+
+    // Every write an instruction makes to RAM goes through here. They're recorded, so they can
+    // be reported by `last_writes`, checked for landing in code when `set_smc_check` is on,
+    // and passed to the hook from `set_write_hook`.
+    // Addresses past the end of RAM wrap around to the start on purpose, like `read_ram`, since
+    // I can point anywhere and FX33/FX55 carry on past it. They're reported wrapped.
+    fn write_ram(&mut self, addr: usize, val: u8) {
+        let addr = addr % self.ram.len();
+        let old = mem::replace(&mut self.ram[addr], val);
+
+        let write = RamWrite {
+            addr: addr as u16,
+            old,
+            new: val,
+        };
+        self.ram_writes.push(write);
+
+        if let Some(smc) = &mut self.smc
+            && addr < smc.code_end
+        {
+            smc.writes.push(CodeWrite { pc: smc.pc, write });
+        }
+
+        if let Some(hook) = &mut self.write_hook {
+            hook(write);
+        }
+    }
+
+    fn clear_planes(&mut self, planes: u8) {
+        for plane in 0..NUM_PLANES {
+            if planes & 1 << plane != 0 {
+                self.plane_mut(plane).fill(0);
+            }
+        }
+    }
+
+    // Rows of one bit plane of the screen
+    fn plane(&self, plane: usize) -> &[u128] {
+        &self.screen[plane * HIRES_SCREEN_HEIGHT..(plane + 1) * HIRES_SCREEN_HEIGHT]
+    }
+
+    fn plane_mut(&mut self, plane: usize) -> &mut [u128] {
+        &mut self.screen[plane * HIRES_SCREEN_HEIGHT..(plane + 1) * HIRES_SCREEN_HEIGHT]
+    }
+
+    // Plane indices in the selected plane mask
+    fn selected_planes(&self) -> impl Iterator<Item = usize> + use<> {
+        let planes = self.planes;
+
+        (0..NUM_PLANES).filter(move |plane| planes & 1 << plane != 0)
+    }
+
+    // Bit of a screen row for the pixel in column x
+    fn column_bit(x: usize) -> u128 {
+        1 << (HIRES_SCREEN_WIDTH - 1 - x)
+    }
+
+    // Bits of a screen row that are part of the active area
+    fn row_mask(&self) -> u128 {
+        let (width, _) = self.display_size();
+
+        !0 << (HIRES_SCREEN_WIDTH - width)
+    }
+
+    // Scrolls shift the active area of the selected planes, filling vacated pixels with unset
+    // ones
+    fn scroll_down(&mut self, lines: usize) {
+        let (_, height) = self.display_size();
+        let lines = lines.min(height);
+
+        for plane in self.selected_planes() {
+            let rows = self.plane_mut(plane);
+
+            rows.copy_within(..height - lines, lines);
+            rows[..lines].fill(0);
+        }
+    }
+
+    fn scroll_right(&mut self, pixels: usize) {
+        let (_, height) = self.display_size();
+        let row_mask = self.row_mask();
+
+        for plane in self.selected_planes() {
+            for row in &mut self.plane_mut(plane)[..height] {
+                *row = (*row >> pixels) & row_mask;
+            }
+        }
+    }
+
+    fn scroll_left(&mut self, pixels: usize) {
+        let (_, height) = self.display_size();
+
+        for plane in self.selected_planes() {
+            for row in &mut self.plane_mut(plane)[..height] {
+                *row <<= pixels;
+            }
+        }
+    }
+
+    fn horizontal_scroll_amount(&self) -> usize {
+        if !self.hires && self.quirks.half_scroll_in_lores {
+            2
+        } else {
+            4
+        }
+    }
+
+    // The original interpreter clobbers VF in 8XY1/8XY2/8XY3
+    fn logic_vf_reset(&mut self) {
+        if self.quirks.vf_reset_on_logic {
+            self.v_reg[0xF] = 0;
         }
     }
 
-    fn push(&mut self, val: u16) {
-        debug_assert!((self.sp as usize) < STACK_SIZE, "stack pointer overflowed!");
+    fn push(&mut self, val: u16) -> Result<(), EmuError> {
+        if self.sp as usize == STACK_SIZE {
+            return Err(EmuError::StackOverflow);
+        }
 
         self.stack[self.sp as usize] = val;
 
         self.sp += 1;
+
+        Ok(())
     }
 
-    fn pop(&mut self) -> u16 {
-        debug_assert!((self.sp as usize) > 0, "stack pointer underflowed!");
+    fn pop(&mut self) -> Result<u16, EmuError> {
+        if self.sp == 0 {
+            return Err(EmuError::StackUnderflow);
+        }
 
         self.sp -= 1;
 
-        self.stack[self.sp as usize]
+        Ok(self.stack[self.sp as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    use super::*;
+    use crate::{
+        asm::assemble,
+        testing::{assert_screen, run, run_rom},
+    };
+
+    // Loaded, but with nothing run yet, for tests that need to do something between
+    // instructions
+    fn emu_with(src: &str) -> Emu {
+        run(src, 0)
+    }
+
+    // Like `run`, with other quirks
+    fn run_with(quirks: Quirks, src: &str, ticks: usize) -> Emu {
+        let mut emu = emu_with(src);
+        emu.set_quirks(quirks);
+        tick_n(&mut emu, ticks);
+
+        emu
+    }
+
+    fn tick_n(emu: &mut Emu, n: usize) {
+        for _ in 0..n {
+            emu.tick().unwrap();
+        }
+    }
+
+    // Runs a program ending in a skip, and whether the skip was taken
+    fn skips(src: &str) -> bool {
+        let src = format!("{src}\nLD VE, 1");
+        let ticks = src.lines().count();
+
+        run(&src, ticks).registers()[0xE] == 0
+    }
+
+    // A single lit pixel to draw, after the code that draws it at (V0, V1)
+    const DOT: &str = "
+        LD I, dot
+        DRW V0, V1, 1
+        JP end
+        dot: DB 0x80
+        end:
+    ";
+
+    #[test]
+    fn draws_sprite() {
+        // The font's 0 at (0, 0), then its 1 to the right of it
+        let emu = run(
+            "
+            LD F, V0
+            DRW V0, V0, 5
+            LD V1, 1
+            LD F, V1
+            LD V2, 5
+            DRW V2, V0, 5
+            ",
+            6,
+        );
+
+        assert_screen(
+            &emu,
+            "
+            ####...#.
+            #..#..##.
+            #..#...#.
+            #..#...#.
+            ####..###
+            ",
+        );
+        assert_eq!(emu.registers()[0xF], 0);
+    }
+
+    #[test]
+    fn draws_sprites_upright() {
+        // Rows go down the screen and bits across it, so this L shape isn't transposed
+        let emu = run(
+            "
+            LD V0, 1
+            LD V1, 2
+            LD I, sprite
+            DRW V0, V1, 3
+            JP end
+            sprite: DB 0xE0, 0x80, 0x80
+            end:
+            ",
+            4,
+        );
+
+        assert_screen(
+            &emu,
+            "
+            ....
+            ....
+            .###
+            .#..
+            .#..
+            ",
+        );
+    }
+
+    #[test]
+    fn draws_sprite_bits_left_to_right() {
+        // The font's 0 from its pattern in RAM, and as the 0 looks the same mirrored, a byte
+        // with only its low bit set, which belongs at the right edge of the sprite
+        let emu = run(
+            "
+            LD F, V0
+            DRW V0, V0, 5
+            LD I, sprite
+            LD V1, 5
+            DRW V1, V0, 1
+            JP end
+            sprite: DB 0x01
+            end:
+            ",
+            5,
+        );
+
+        assert_screen(
+            &emu,
+            "
+            ####........#
+            #..#
+            #..#
+            #..#
+            ####
+            ",
+        );
+        assert_eq!(emu.memory()[..5], [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+    }
+
+    // The display index of the only lit pixel after drawing a dot at (x, y)
+    fn dot_index(x: u8, y: u8, hires: bool) -> usize {
+        let mode = if hires { "HIGH" } else { "LOW" };
+        let emu = run(&format!("{mode}\nLD V0, {x}\nLD V1, {y}\n{DOT}"), 6);
+
+        let lit: Vec<_> = emu
+            .get_display()
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, &pixel)| pixel.then_some(idx))
+            .collect();
+        assert_eq!(lit.len(), 1);
+
+        lit[0]
+    }
+
+    #[test]
+    fn draws_to_the_last_pixel() {
+        assert_eq!(dot_index(63, 31, false), 64 * 32 - 1);
+        assert_eq!(dot_index(127, 63, true), 128 * 64 - 1);
+
+        // One past either edge wraps back around
+        assert_eq!(dot_index(64, 31, false), 64 * 31);
+        assert_eq!(dot_index(63, 32, false), 63);
+        assert_eq!(dot_index(128, 64, true), 0);
+    }
+
+    #[test]
+    fn switches_the_drawable_area() {
+        // A dot at (100, 50) only fits on the hi-res display
+        let mut emu = emu_with(&format!("HIGH\nLD V0, 100\nLD V1, 50\n{DOT}\nLOW"));
+        assert_eq!(emu.display_size(), (64, 32));
+        assert_eq!(emu.get_display().len(), 64 * 32);
+
+        tick_n(&mut emu, 5);
+
+        assert_eq!(emu.display_size(), (128, 64));
+        assert_eq!(emu.get_display().len(), 128 * 64);
+        assert!(emu.get_display()[50 * 128 + 100]);
+
+        // Back in lo-res, it's gone with the rest of the hi-res screen
+        tick_n(&mut emu, 2);
+
+        assert_eq!(emu.display_size(), (64, 32));
+        assert_eq!(emu.get_display().len(), 64 * 32);
+        assert_screen(&emu, "");
+    }
+
+    #[test]
+    fn draws_custom_fonts() {
+        // A square for the 2
+        let mut fontset = [0; FONTSET_SIZE];
+        fontset[10..15].copy_from_slice(&[0xF8, 0x88, 0x88, 0x88, 0xF8]);
+
+        let mut emu = Emu::new_with_seed(0);
+        emu.set_fontset(&fontset).unwrap();
+        emu.load(&assemble("LD V0, 2\nLD F, V0\nDRW V1, V1, 5").unwrap())
+            .unwrap();
+
+        // It outlasts a reset
+        emu.reset();
+        tick_n(&mut emu, 3);
+
+        assert_screen(
+            &emu,
+            "
+            #####
+            #...#
+            #...#
+            #...#
+            #####
+            ",
+        );
+
+        assert_eq!(
+            emu.set_fontset(&fontset[1..]),
+            Err(EmuError::WrongFontsetSize {
+                size: FONTSET_SIZE - 1,
+                expected: FONTSET_SIZE
+            })
+        );
+    }
+
+    #[test]
+    fn flags_collisions() {
+        // VF starts at 1 to show it's cleared, then the same dot drawn twice
+        let mut emu = emu_with(&format!(
+            "LD VF, 1\nLD V0, 3\n{DOT}\nLD I, dot\nDRW V0, V1, 1"
+        ));
+        tick_n(&mut emu, 4);
+
+        assert_eq!(emu.registers()[0xF], 0);
+        assert!(emu.get_display()[3]);
+
+        tick_n(&mut emu, 3);
+
+        assert_eq!(emu.registers()[0xF], 1);
+        assert!(!emu.get_display()[3]);
+    }
+
+    // (x, y) of each lit pixel, row by row
+    fn lit_pixels(emu: &Emu) -> Vec<(usize, usize)> {
+        let (width, _) = emu.display_size();
+
+        emu.get_display()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &pixel)| pixel)
+            .map(|(idx, _)| (idx % width, idx / width))
+            .collect()
+    }
+
+    #[test]
+    fn draws_16x16_sprites_in_hires() {
+        // A solid block at (1, 2), then again 8 pixels to the right so the two halves overlap
+        let mut emu = emu_with(
+            "
+            HIGH
+            LD V0, 1
+            LD V1, 2
+            LD I, block
+            DRW V0, V1, 0
+            LD V0, 9
+            DRW V0, V1, 0
+            JP end
+            block: DB 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF
+            DB 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF
+            end:
+            ",
+        );
+        tick_n(&mut emu, 5);
+
+        let block: Vec<_> = (2..18).flat_map(|y| (1..17).map(move |x| (x, y))).collect();
+        assert_eq!(lit_pixels(&emu), block);
+        assert_eq!(emu.registers()[0xF], 0);
+
+        tick_n(&mut emu, 2);
+
+        let halves: Vec<_> = (2..18)
+            .flat_map(|y| (1..9).chain(17..25).map(move |x| (x, y)))
+            .collect();
+        assert_eq!(lit_pixels(&emu), halves);
+        assert_eq!(emu.registers()[0xF], 1);
+    }
+
+    #[test]
+    fn clears_screen() {
+        let emu = run(
+            "
+            LD F, V0
+            DRW V0, V0, 5
+            CLS
+            ",
+            3,
+        );
+
+        assert_screen(&emu, "");
+    }
+
+    #[test]
+    fn scrolls_down() {
+        let emu = run(&format!("LD V0, 2\nLD V1, 1\n{DOT}\nSCD 2"), 6);
+
+        assert_screen(
+            &emu,
+            "
+            ...
+            ...
+            ...
+            ..#
+            ",
+        );
+    }
+
+    #[test]
+    fn scrolls_right() {
+        let emu = run(&format!("LD V0, 2\nLD V1, 1\n{DOT}\nSCR"), 6);
+
+        assert_screen(
+            &emu,
+            "
+            .......
+            ......#
+            ",
+        );
+    }
+
+    #[test]
+    fn scrolls_left() {
+        let emu = run(&format!("LD V0, 6\nLD V1, 1\n{DOT}\nSCL"), 6);
+
+        assert_screen(
+            &emu,
+            "
+            ...
+            ..#
+            ",
+        );
+    }
+
+    // Where a dot drawn at (6, 1) ends up after a scroll
+    fn scrolled_dot(half_scroll_in_lores: bool, hires: bool, scroll: &str) -> (usize, usize) {
+        let mode = if hires { "HIGH" } else { "LOW" };
+        let quirks = Quirks {
+            half_scroll_in_lores,
+            ..Quirks::default()
+        };
+        let emu = run_with(
+            quirks,
+            &format!("{mode}\nLD V0, 6\nLD V1, 1\n{DOT}\n{scroll}"),
+            7,
+        );
+
+        let (width, _) = emu.display_size();
+        let idx = emu.get_display().iter().position(|&pixel| pixel).unwrap();
+
+        (idx % width, idx / width)
+    }
+
+    #[test]
+    fn scrolls_in_hires() {
+        assert_eq!(scrolled_dot(false, true, "SCD 3"), (6, 4));
+        assert_eq!(scrolled_dot(false, true, "SCR"), (10, 1));
+        assert_eq!(scrolled_dot(false, true, "SCL"), (2, 1));
+    }
+
+    #[test]
+    fn scrolls_by_half_in_lores_with_the_quirk() {
+        assert_eq!(scrolled_dot(true, false, "SCR"), (8, 1));
+        assert_eq!(scrolled_dot(true, false, "SCL"), (4, 1));
+        assert_eq!(scrolled_dot(false, false, "SCR"), (10, 1));
+        assert_eq!(scrolled_dot(false, false, "SCL"), (2, 1));
+
+        // Hi-res scrolls stay at 4 pixels
+        assert_eq!(scrolled_dot(true, true, "SCR"), (10, 1));
+        assert_eq!(scrolled_dot(true, true, "SCL"), (2, 1));
+
+        // Scrolling down is the same in both
+        assert_eq!(scrolled_dot(true, false, "SCD 3"), (6, 4));
+    }
+
+    #[test]
+    fn scrolls_drop_pixels_off_the_edges() {
+        let emu = run(&format!("LD V0, 1\nLD V1, 30\n{DOT}\nSCD 2\nSCL"), 7);
+
+        assert_screen(&emu, "");
+    }
+
+    // Where a 2x2 block drawn in the bottom right corner ends up with the wrap_x/wrap_y quirks
+    fn corner_block(wrap_x: bool, wrap_y: bool) -> Vec<(usize, usize)> {
+        let mut emu = emu_with(
+            "
+            LD V0, 63
+            LD V1, 31
+            LD I, block
+            DRW V0, V1, 2
+            JP end
+            block: DB 0xC0, 0xC0
+            end:
+            ",
+        );
+        emu.set_quirks(Quirks {
+            wrap_x,
+            wrap_y,
+            ..Quirks::default()
+        });
+        tick_n(&mut emu, 4);
+
+        let (width, _) = emu.display_size();
+
+        emu.get_display()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &pixel)| pixel)
+            .map(|(idx, _)| (idx % width, idx / width))
+            .collect()
+    }
+
+    #[test]
+    fn wraps_sprites_off_the_edges() {
+        assert_eq!(
+            corner_block(true, true),
+            [(0, 0), (63, 0), (0, 31), (63, 31)]
+        );
+    }
+
+    #[test]
+    fn clips_sprites_off_the_right_edge() {
+        assert_eq!(corner_block(false, true), [(63, 0), (63, 31)]);
+    }
+
+    #[test]
+    fn clips_sprites_off_the_bottom_edge() {
+        assert_eq!(corner_block(true, false), [(0, 31), (63, 31)]);
+    }
+
+    #[test]
+    fn clips_sprites_off_both_edges() {
+        assert_eq!(corner_block(false, false), [(63, 31)]);
+    }
+
+    #[test]
+    fn stops_at_the_top_of_ram() {
+        // A JP 0x2F0 split over the last byte of RAM and the first, the top of the font's 0
+        let mut rom = vec![0; RAM_SIZE - START_ADDR as usize];
+        *rom.last_mut().unwrap() = 0x12;
+
+        let mut emu = run_rom(&rom, 0);
+        emu.set_program_counter(RAM_SIZE as u16 - 1);
+
+        assert_eq!(emu.tick(), Err(EmuError::PcOutOfBounds(0xFFF)));
+
+        emu.set_quirks(Quirks {
+            wrap_pc: true,
+            ..Quirks::default()
+        });
+        emu.tick().unwrap();
+
+        assert_eq!(emu.program_counter(), 0x2F0);
+    }
+
+    #[test]
+    fn clips_or_wraps_sprites_partly_off_the_right() {
+        let src = "
+            LD V0, 60
+            LD I, line
+            DRW V0, V1, 1
+            JP end
+            line: DB 0xFF
+            end:
+        ";
+        let top_row = |emu: Emu| emu.display_text().lines().next().unwrap().to_string();
+        let clip = Quirks {
+            wrap_x: false,
+            ..Quirks::default()
+        };
+
+        assert_eq!(top_row(run(src, 3)), format!("####{}####", ".".repeat(56)));
+        assert_eq!(
+            top_row(run_with(clip, src, 3)),
+            format!("{}####", ".".repeat(60))
+        );
+    }
+
+    #[test]
+    fn jumps() {
+        let emu = run("JP over\nLD V0, 1\nover: LD V1, 1", 2);
+
+        assert_eq!(emu.registers()[..2], [0, 1]);
+        assert_eq!(emu.program_counter(), 0x206);
+    }
+
+    #[test]
+    fn calls_and_returns() {
+        let emu = run(
+            "
+            CALL sub
+            LD V1, 2
+            JP end
+            sub: LD V0, 1
+            RET
+            end:
+            ",
+            5,
+        );
+
+        assert_eq!(emu.registers()[..2], [1, 2]);
+        assert_eq!(emu.program_counter(), 0x20A);
+        assert_eq!(emu.stack_pointer(), 0);
+    }
+
+    #[test]
+    fn returns_without_a_call() {
+        let mut emu = emu_with("RET");
+
+        assert_eq!(emu.tick(), Err(EmuError::StackUnderflow));
+    }
+
+    #[test]
+    fn nests_calls_until_the_stack_is_full() {
+        // Calls itself forever
+        let mut emu = emu_with("sub: CALL sub");
+        tick_n(&mut emu, STACK_SIZE);
+        assert_eq!(emu.stack_pointer() as usize, STACK_SIZE);
+
+        assert_eq!(emu.tick(), Err(EmuError::StackOverflow));
+    }
+
+    #[test]
+    fn skips_on_comparisons() {
+        assert!(skips("LD V0, 5\nSE V0, 5"));
+        assert!(!skips("LD V0, 5\nSE V0, 6"));
+        assert!(skips("LD V0, 5\nSNE V0, 6"));
+        assert!(!skips("LD V0, 5\nSNE V0, 5"));
+        assert!(skips("LD V0, 5\nLD V1, 5\nSE V0, V1"));
+        assert!(!skips("LD V0, 5\nSE V0, V1"));
+        assert!(skips("LD V0, 5\nSNE V0, V1"));
+        assert!(!skips("LD V0, 5\nLD V1, 5\nSNE V0, V1"));
+    }
+
+    #[test]
+    fn loads_and_adds_immediates() {
+        let emu = run("LD V3, 0xFE\nADD V3, 3\nLD V4, 7", 3);
+
+        // 7XNN wraps around, and leaves VF alone
+        assert_eq!(emu.registers()[3..5], [1, 7]);
+        assert_eq!(emu.registers()[0xF], 0);
+    }
+
+    // V0 after `op V0, V1` on the given values, and VF
+    fn arithmetic(op: &str, v0: u8, v1: u8) -> (u8, u8) {
+        arithmetic_with(Quirks::default(), op, v0, v1)
+    }
+
+    fn arithmetic_with(quirks: Quirks, op: &str, v0: u8, v1: u8) -> (u8, u8) {
+        let emu = run_with(quirks, &format!("LD V0, {v0}\nLD V1, {v1}\n{op} V0, V1"), 3);
+
+        (emu.registers()[0], emu.registers()[0xF])
+    }
+
+    #[test]
+    fn does_register_arithmetic() {
+        assert_eq!(arithmetic("LD", 0b1100, 0b1010), (0b1010, 0));
+        assert_eq!(arithmetic("OR", 0b1100, 0b1010), (0b1110, 0));
+        assert_eq!(arithmetic("AND", 0b1100, 0b1010), (0b1000, 0));
+        assert_eq!(arithmetic("XOR", 0b1100, 0b1010), (0b0110, 0));
+
+        // VF is the carry, or for subtraction 1 when there's no borrow
+        assert_eq!(arithmetic("ADD", 200, 50), (250, 0));
+        assert_eq!(arithmetic("ADD", 0xFF, 2), (1, 1));
+        assert_eq!(arithmetic("SUB", 7, 5), (2, 1));
+        assert_eq!(arithmetic("SUB", 5, 7), (0xFE, 0));
+        assert_eq!(arithmetic("SUBN", 5, 7), (2, 1));
+        assert_eq!(arithmetic("SUBN", 7, 5), (0xFE, 0));
+
+        // VF is the bit shifted out, and VY is ignored by default
+        assert_eq!(arithmetic("SHR", 0b101, 0xFF), (0b10, 1));
+        assert_eq!(arithmetic("SHR", 0b100, 0xFF), (0b10, 0));
+        assert_eq!(arithmetic("SHL", 0x81, 0xFF), (0x02, 1));
+        assert_eq!(arithmetic("SHL", 0x41, 0xFF), (0x82, 0));
+    }
+
+    #[test]
+    fn shifts_vy_with_the_quirk() {
+        let quirks = Quirks {
+            shift_uses_vy: true,
+            ..Quirks::default()
+        };
+
+        assert_eq!(arithmetic("SHR", 0x81, 0x06), (0x40, 1));
+        assert_eq!(arithmetic_with(quirks, "SHR", 0x81, 0x06), (0x03, 0));
+        assert_eq!(arithmetic("SHL", 0x81, 0x06), (0x02, 1));
+        assert_eq!(arithmetic_with(quirks, "SHL", 0x81, 0x06), (0x0C, 0));
+    }
+
+    #[test]
+    fn increments_i_with_the_quirk() {
+        let quirks = Quirks {
+            increment_i_on_store_load: true,
+            ..Quirks::default()
+        };
+
+        for op in ["LD [I], V2", "LD V2, [I]"] {
+            let src = format!("LD I, 0x300\n{op}");
+
+            assert_eq!(run(&src, 2).i_register(), 0x300, "{op}");
+            assert_eq!(run_with(quirks, &src, 2).i_register(), 0x303, "{op}");
+        }
+    }
+
+    #[test]
+    fn jumps_with_vx_with_the_quirk() {
+        let src = "LD V0, 2\nLD V3, 4\nJP V0, 0x320";
+        let quirks = Quirks {
+            jump_with_vx: true,
+            ..Quirks::default()
+        };
+
+        assert_eq!(run(src, 3).program_counter(), 0x322);
+        // BXNN, with X = 3 and NN = 0x20
+        assert_eq!(run_with(quirks, src, 3).program_counter(), 0x24);
+    }
+
+    #[test]
+    fn resets_vf_on_logic_with_the_quirk() {
+        let quirks = Quirks {
+            vf_reset_on_logic: true,
+            ..Quirks::default()
+        };
+
+        for op in ["OR", "AND", "XOR"] {
+            let src = format!("LD VF, 1\n{op} V0, V1");
+
+            assert_eq!(run(&src, 2).registers()[0xF], 1, "{op}");
+            assert_eq!(run_with(quirks, &src, 2).registers()[0xF], 0, "{op}");
+        }
+    }
+
+    #[test]
+    fn sets_i_and_jumps_with_offset() {
+        let emu = run("LD I, 0x345\nLD V0, 4\nJP V0, 0x300", 3);
+
+        assert_eq!(emu.i_register(), 0x345);
+        assert_eq!(emu.program_counter(), 0x304);
+    }
+
+    #[test]
+    fn masks_random_numbers() {
+        let emu = run("RND V0, 0x0F\nRND V1, 0", 2);
+
+        assert_eq!(emu.registers()[0] & 0xF0, 0);
+        assert_eq!(emu.registers()[1], 0);
+
+        // Seeded the same every time
+        assert_eq!(run("RND V0, 0xFF", 1).registers()[0], {
+            let mut rng = StdRng::seed_from_u64(0);
+            rng.random::<u8>()
+        });
+    }
+
+    #[test]
+    fn skips_on_keys() {
+        let src = "LD V0, 5\nSKP V0\nLD V1, 1\nSKNP V0\nLD V2, 1";
+
+        let mut emu = emu_with(src);
+        tick_n(&mut emu, 5);
+        assert_eq!(emu.registers()[1..3], [1, 0]);
+
+        let mut emu = emu_with(src);
+        emu.keypress(5, true);
+        tick_n(&mut emu, 4);
+        assert_eq!(emu.registers()[1..3], [0, 1]);
+    }
+
+    #[test]
+    fn waits_for_key_releases() {
+        let mut emu = emu_with("LD V3, K");
+        tick_n(&mut emu, 2);
+        assert_eq!(emu.program_counter(), START_ADDR);
+
+        // Holding the key isn't enough
+        emu.keypress(7, true);
+        tick_n(&mut emu, 2);
+        assert_eq!(emu.program_counter(), START_ADDR);
+
+        emu.keypress(7, false);
+        emu.tick().unwrap();
+        assert_eq!(emu.registers()[3], 7);
+        assert_eq!(emu.program_counter(), START_ADDR + 2);
+    }
+
+    #[test]
+    fn waits_for_key_presses_with_the_quirk() {
+        let quirks = Quirks {
+            key_wait_on_press: true,
+            ..Quirks::default()
+        };
+        let mut emu = run_with(quirks, "LD V3, K", 2);
+        assert_eq!(emu.program_counter(), START_ADDR);
+
+        emu.keypress(7, true);
+        emu.tick().unwrap();
+        assert_eq!(emu.registers()[3], 7);
+        assert_eq!(emu.program_counter(), START_ADDR + 2);
+    }
+
+    #[test]
+    fn waits_for_keys_at_the_top_of_ram() {
+        // A jump to an FX0A in the last two bytes of RAM, so the PC has wrapped around to 0 by
+        // the time it waits
+        let mut rom = vec![0; RAM_SIZE - START_ADDR as usize];
+        rom[..2].copy_from_slice(&[0x1F, 0xFE]);
+        rom[RAM_SIZE - START_ADDR as usize - 2..].copy_from_slice(&[0xF3, 0x0A]);
+
+        let mut emu = run_rom(&rom, 0);
+        emu.set_quirks(Quirks {
+            wrap_pc: true,
+            ..Quirks::default()
+        });
+        emu.set_idle_skip(true);
+        emu.run_frames(2).unwrap();
+
+        assert_eq!(emu.program_counter(), 0xFFE);
+
+        emu.keypress(7, true);
+        emu.run_frame().unwrap();
+        emu.keypress(7, false);
+        emu.tick().unwrap();
+
+        assert_eq!(emu.registers()[3], 7);
+        assert_eq!(emu.program_counter(), 0);
+    }
+
+    #[test]
+    fn uses_timers() {
+        let mut emu = emu_with("LD V0, 9\nLD DT, V0\nLD ST, V0\nLD V1, DT");
+        tick_n(&mut emu, 3);
+        emu.tick_timers();
+        tick_n(&mut emu, 1);
+
+        assert_eq!(emu.registers()[1], 8);
+        assert_eq!(emu.sound_timer(), 8);
+        assert!(emu.is_beeping());
+    }
+
+    #[test]
+    fn points_i_at_memory() {
+        let emu = run("LD I, 0x300\nLD V0, 0x20\nADD I, V0", 3);
+        assert_eq!(emu.i_register(), 0x320);
+
+        let emu = run("LD V0, 0xA\nLD F, V0", 2);
+        assert_eq!(emu.i_register(), 0xA * 5);
+    }
+
+    #[test]
+    fn points_i_at_large_digits() {
+        let emu = run("LD V3, 7\nLD HF, V3", 2);
+
+        let addr = emu.i_register() as usize;
+        assert_eq!(addr, LARGE_FONTSET_ADDR + 7 * 10);
+        assert_eq!(emu.memory()[addr..addr + 10], LARGE_FONTSET[70..80]);
+    }
+
+    #[test]
+    fn stores_bcd() {
+        let emu = run("LD I, 0x300\nLD V0, 254\nLD B, V0", 3);
+
+        assert_eq!(emu.memory()[0x300..0x303], [2, 5, 4]);
+    }
+
+    #[test]
+    fn stores_and_loads_registers() {
+        let emu = run(
+            "
+            LD V0, 1
+            LD V1, 2
+            LD V2, 3
+            LD I, 0x300
+            LD [I], V1
+            LD I, 0x2FF
+            LD V2, [I]
+            ",
+            7,
+        );
+
+        // Only up to V1 was stored, so V2 loads the 0 after it
+        assert_eq!(emu.memory()[0x300..0x303], [1, 2, 0]);
+        assert_eq!(emu.registers()[..3], [0, 1, 2]);
+        assert_eq!(emu.i_register(), 0x2FF);
+    }
+
+    #[test]
+    fn stores_and_loads_rpl_flags() {
+        let emu = run(
+            "
+            LD V0, 1
+            LD V1, 2
+            LD V2, 3
+            LD R, V2
+            LD V0, 0
+            LD V1, 0
+            LD V2, 0
+            LD V1, R
+            ",
+            8,
+        );
+
+        // Only up to V1 was loaded back
+        assert_eq!(emu.registers()[..3], [1, 2, 0]);
+    }
+
+    // A trace sink the test can still read after handing it to the emulator
+    #[cfg(feature = "std")]
+    struct SharedSink(Rc<RefCell<Vec<u8>>>);
+
+    #[cfg(feature = "std")]
+    impl Write for SharedSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn traces_each_instruction() {
+        let trace = Rc::new(RefCell::new(Vec::new()));
+        let mut emu = emu_with("LD V0, 5\nADD V0, 2\nLD I, 0x300");
+        emu.set_trace(Box::new(SharedSink(Rc::clone(&trace))));
+        tick_n(&mut emu, 3);
+
+        let trace = String::from_utf8(trace.take()).unwrap();
+        let lines: Vec<_> = trace.lines().collect();
+        assert_eq!(lines.len(), 3);
+
+        let ops: Vec<_> = lines.iter().map(|line| &line[..10]).collect();
+        assert_eq!(ops, ["0200  6005", "0202  7002", "0204  A300"]);
+
+        assert!(lines[0].contains("LD V0, 0x05"));
+        assert!(lines[1].contains("V: 07 00"));
+        assert!(lines[2].contains("I: 0300"));
+    }
+
+    // The writes passed to the write hook while running a program
+    fn hooked_writes(src: &str, ticks: usize) -> Vec<RamWrite> {
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        let mut emu = emu_with(src);
+
+        let hooked = Rc::clone(&writes);
+        emu.set_write_hook(Box::new(move |write| hooked.borrow_mut().push(write)));
+        tick_n(&mut emu, ticks);
+
+        writes.take()
+    }
+
+    #[test]
+    fn hooks_ram_writes() {
+        let write = |addr, old, new| RamWrite { addr, old, new };
+
+        assert_eq!(
+            hooked_writes("LD I, 0x300\nLD V0, 195\nLD B, V0", 3),
+            [write(0x300, 0, 1), write(0x301, 0, 9), write(0x302, 0, 5)]
+        );
+
+        // Wrapping past the end of RAM, onto the top row of the font's 0
+        assert_eq!(
+            hooked_writes("LD I, 0xFFF\nLD V0, 1\nLD V1, 2\nLD [I], V1", 4),
+            [write(0xFFF, 0, 1), write(0x000, 0xF0, 2)]
+        );
+    }
+
+    #[test]
+    fn draws_without_writing_ram() {
+        assert!(hooked_writes(&format!("LD V0, 1\nLD V1, 1\n{DOT}"), 4).is_empty());
+    }
+
+    #[test]
+    fn warns_about_writes_into_code() {
+        let src = "
+            LD I, 0x200
+            LD V0, 0x12
+            LD [I], V0
+            LD I, 0x300
+            LD [I], V0
+        ";
+
+        let mut emu = emu_with(src);
+        emu.set_smc_check(true);
+        tick_n(&mut emu, 5);
+
+        // Only the write over the first instruction, which has run, and not the one past the code
+        assert_eq!(
+            emu.take_code_writes(),
+            [CodeWrite {
+                pc: 0x204,
+                write: RamWrite {
+                    addr: 0x200,
+                    old: 0xA2,
+                    new: 0x12,
+                },
+            }]
+        );
+        assert!(emu.take_code_writes().is_empty());
+
+        // Off by default
+        assert!(run(src, 5).take_code_writes().is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_opcodes_in_every_family() {
+        for op in [0x0123, 0x5001, 0x800F, 0x9001, 0xE000, 0xF0FF] {
+            let mut emu = run_rom(&u16::to_be_bytes(op), 0);
+
+            assert_eq!(emu.tick(), Err(EmuError::UnknownOpcode(op)));
+        }
+    }
+
+    #[test]
+    fn resets_but_keeps_the_program() {
+        let src = "
+            LD V0, 5
+            LD I, 0x300
+            LD DT, V0
+            LD F, V0
+            DRW V0, V0, 5
+            CALL sub
+            sub: LD V1, 1
+        ";
+        let mut emu = run(src, 7);
+        emu.keypress(3, true);
+        emu.reset();
+
+        let fresh = run(src, 0);
+
+        assert_eq!(emu.program_counter(), START_ADDR);
+        assert_eq!(*emu.registers(), [0; NUM_REGS]);
+        assert_eq!(emu.i_register(), 0);
+        assert_eq!(emu.stack_pointer(), 0);
+        assert_eq!(emu.delay_timer(), 0);
+        assert!(emu.get_display().iter().all(|&pixel| !pixel));
+        assert_eq!(emu.save_state(), fresh.save_state());
+
+        // The program is still there to run again
+        assert_eq!(emu.memory(), fresh.memory());
+        tick_n(&mut emu, 1);
+        assert_eq!(emu.registers()[0], 5);
+    }
+
+    #[test]
+    fn counts_hotspots() {
+        // Counts V0 up to 10, then sets V1 once
+        let src = "
+            LD V0, 0
+            loop: ADD V0, 1
+            SE V0, 10
+            JP loop
+            LD V1, 1
+        ";
+        let mut emu = emu_with(src);
+        emu.set_hotspots(true);
+        tick_n(&mut emu, 31);
+
+        assert_eq!(emu.registers()[..2], [10, 1]);
+        assert_eq!(emu.hotspots(1), [(0x202, 10)]);
+        assert_eq!(
+            emu.hotspots(usize::MAX),
+            [(0x202, 10), (0x204, 10), (0x206, 9), (0x200, 1), (0x208, 1)]
+        );
+
+        // Nothing's counted until they're turned on
+        let mut emu = emu_with(src);
+        tick_n(&mut emu, 31);
+        assert_eq!(emu.hotspots(1), []);
+    }
+
+    // How many times each instruction runs over a frame of a program that ends up idle
+    fn frame_hotspots(src: &str, idle_skip: bool) -> Vec<(u16, u64)> {
+        let mut emu = emu_with(src);
+        emu.set_idle_skip(idle_skip);
+        emu.set_hotspots(true);
+        emu.run_frame().unwrap();
+
+        let mut hotspots = emu.hotspots(usize::MAX);
+        hotspots.sort_unstable();
+
+        hotspots
+    }
+
+    #[test]
+    fn skips_the_rest_of_idle_frames() {
+        let spin = "LD V0, 1\nloop: JP loop";
+
+        assert_eq!(frame_hotspots(spin, true), [(0x200, 1), (0x202, 1)]);
+        assert_eq!(frame_hotspots(spin, false), [(0x200, 1), (0x202, 9)]);
+
+        // Waiting for a key too
+        assert_eq!(frame_hotspots("LD V0, K", true), [(0x200, 1)]);
+
+        // But not a loop that's getting somewhere
+        let count = "loop: ADD V0, 1\nJP loop";
+        assert_eq!(frame_hotspots(count, true), [(0x200, 5), (0x202, 5)]);
+    }
+
+    #[test]
+    fn dumps_state_as_text() {
+        let emu = run(
+            "
+            LD V0, 0xAB
+            LD VF, 1
+            LD I, 0x123
+            LD DT, V0
+            CALL sub
+            sub: LD F, VF
+            DRW V1, V1, 1
+            ",
+            7,
+        );
+        let dump = emu.debug_dump();
+        let lines: Vec<_> = dump.lines().collect();
+
+        assert_eq!(
+            lines[..5],
+            [
+                "PC 020E  I 0005  SP 1  DT AB  ST 00",
+                "V0 AB  V1 00  V2 00  V3 00  V4 00  V5 00  V6 00  V7 00",
+                "V8 00  V9 00  VA 00  VB 00  VC 00  VD 00  VE 00  VF 00",
+                "Stack 020A",
+                "Display 64x32",
+            ]
+        );
+        assert_eq!(lines[5..].join("\n") + "\n", emu.display_text());
+        assert!(lines[5].starts_with("..#....."));
+
+        assert!(run("", 0).debug_dump().contains("\nStack empty\n"));
+    }
+
+    #[test]
+    fn restores_saved_state() {
+        let mut emu = run_rom(include_bytes!("../roms/bounce.ch8"), 0);
+        emu.run_frames(10).unwrap();
+
+        let state = emu.save_state();
+        let hash = emu.display_hash();
+
+        emu.run_frames(10).unwrap();
+        assert_ne!(emu.display_hash(), hash);
+
+        emu.load_state(&state).unwrap();
+        assert_eq!(emu.save_state(), state);
+        assert_eq!(emu.display_hash(), hash);
+    }
+
+    #[test]
+    fn rejects_invalid_save_states() {
+        let mut emu = Emu::new();
+
+        let corrupt: [fn(&mut Emu); 4] = [
+            |state| state.sp = STACK_SIZE as u16 + 1,
+            |state| state.ram.clear(),
+            |state| state.pc = RAM_SIZE as u16,
+            |state| state.start_addr = RAM_SIZE as u16,
+        ];
+
+        for corrupt in corrupt {
+            let mut state = Emu::new();
+            corrupt(&mut state);
+
+            assert_eq!(
+                emu.load_state(&state.save_state()),
+                Err(EmuError::InvalidSaveState)
+            );
+        }
+
+        // Saved with a different amount of RAM
+        let state = Emu::new_with_config(EmuConfig::xo_chip()).save_state();
+        assert_eq!(emu.load_state(&state), Err(EmuError::InvalidSaveState));
+
+        assert_eq!(emu.load_state(&[1, 2, 3]), Err(EmuError::InvalidSaveState));
+
+        // Nothing was changed by any of them
+        assert_eq!(emu.save_state(), Emu::new().save_state());
+    }
+
+    #[test]
+    fn rewinds_frames() {
+        let mut emu = run_rom(include_bytes!("../roms/bounce.ch8"), 0);
+
+        // Nothing is kept unless asked for
+        emu.run_frames(5).unwrap();
+        assert!(!emu.rewind());
+
+        emu.set_rewind_depth(3);
+
+        let mut states = Vec::new();
+
+        for _ in 0..10 {
+            emu.run_frame().unwrap();
+            states.push(emu.save_state());
+        }
+
+        // Back through the last 3 frames, newest first
+        for state in states.iter().rev().take(3) {
+            assert!(emu.rewind());
+            assert_eq!(&emu.save_state(), state);
+        }
+
+        assert!(!emu.rewind());
+        assert_eq!(emu.save_state(), states[7]);
+    }
+
+    #[test]
+    fn drops_rewind_history_on_reset_and_load() {
+        let mut emu = run_rom(include_bytes!("../roms/bounce.ch8"), 0);
+        emu.set_rewind_depth(3);
+
+        emu.run_frames(5).unwrap();
+        emu.reset();
+        assert!(!emu.rewind());
+
+        emu.run_frames(5).unwrap();
+        emu.load(include_bytes!("../roms/keys.ch8")).unwrap();
+        assert!(!emu.rewind());
+
+        // Loading a state keeps it, and history builds up again as before
+        emu.run_frames(5).unwrap();
+        let state = emu.save_state();
+        emu.load_state(&state).unwrap();
+        assert!(emu.rewind());
+    }
+
+    #[test]
+    fn drops_rewind_history_when_ram_grows() {
+        let mut emu = run_rom(include_bytes!("../roms/bounce.ch8"), 0);
+        emu.set_rewind_depth(3);
+        emu.run_frames(5).unwrap();
+
+        // The same size keeps it
+        emu.grow_ram(RAM_SIZE);
+        assert!(emu.rewind());
+
+        emu.grow_ram(0x10000);
+        assert!(!emu.rewind());
+        assert_eq!(emu.memory().len(), 0x10000);
+
+        // And it's kept again from there
+        emu.run_frames(2).unwrap();
+        assert!(emu.rewind());
+        assert_eq!(emu.memory().len(), 0x10000);
+    }
+
+    fn run_xo_chip(src: &str, ticks: usize) -> Emu {
+        let mut emu = Emu::new_with_config(EmuConfig::xo_chip());
+        emu.load(&assemble(src).unwrap()).unwrap();
+        tick_n(&mut emu, ticks);
+
+        emu
+    }
+
+    #[test]
+    fn loads_long_addresses() {
+        let emu = run_xo_chip("LD I, LONG 0xABCD\nLD V0, 1", 1);
+
+        assert_eq!(emu.i_register(), 0xABCD);
+        assert_eq!(emu.program_counter(), 0x204);
+
+        // Skips go over both words of it
+        let emu = run_xo_chip("SE V0, 0\nLD I, LONG 0xABCD\nLD V0, 1", 2);
+        assert_eq!(emu.i_register(), 0);
+        assert_eq!(emu.registers()[0], 1);
+    }
+
+    #[test]
+    fn uses_memory_past_4k() {
+        let emu = run_xo_chip(
+            "
+            LD I, LONG 0x8000
+            LD V0, 0xF0
+            LD [I], V0
+            LD I, LONG 0x8000
+            LD V0, 0
+            DRW V0, V0, 1
+            LD V1, 0xFF
+            ADD I, V1
+            LD B, V1
+            ",
+            9,
+        );
+
+        assert_eq!(emu.memory()[0x8000], 0xF0);
+        assert_eq!(emu.memory()[0x80FF..0x8102], [2, 5, 5]);
+        assert_eq!(emu.memory()[..FONTSET_SIZE], FONTSET);
+        assert_eq!(emu.i_register(), 0x80FF);
+        assert_eq!(emu.get_display()[..5], [true, true, true, true, false]);
+    }
+
+    // Draws a pixel into plane 1 at (0, 0), plane 2 at (1, 0), and both at (2, 0) and (3, 0)
+    // with plane 2 only getting the second, then does `then` with the given planes selected
+    fn draw_planes(then: &str, planes: u8) -> Emu {
+        let src = format!(
+            "
+            LD I, dot
+            DRW V0, V0, 1
+            PLANE 2
+            LD V1, 1
+            DRW V1, V0, 1
+            PLANE 3
+            LD V1, 2
+            LD I, pair
+            DRW V1, V0, 1
+            PLANE {planes}
+            {then}
+            JP end
+            dot: DB 0x80
+            pair: DB 0xC0, 0x40
+            end:
+            "
+        );
+
+        run_xo_chip(&src, 10 + then.lines().count())
+    }
+
+    #[test]
+    fn draws_into_each_plane() {
+        let emu = draw_planes("NOP", 1);
+
+        assert_eq!(emu.get_display_planes()[..5], [1, 2, 1, 3, 0]);
+        assert_eq!(emu.get_display()[..5], [true, true, true, true, false]);
+    }
+
+    #[test]
+    fn clears_the_selected_planes() {
+        assert_eq!(
+            draw_planes("CLS", 1).get_display_planes()[..5],
+            [0, 2, 0, 2, 0]
+        );
+        assert_eq!(
+            draw_planes("CLS", 2).get_display_planes()[..5],
+            [1, 0, 1, 1, 0]
+        );
+        assert!(
+            draw_planes("CLS", 3)
+                .get_display()
+                .iter()
+                .all(|&pixel| !pixel)
+        );
+    }
+
+    #[test]
+    fn draws_nothing_without_planes() {
+        let emu = draw_planes("LD I, dot\nLD V1, 4\nDRW V1, V0, 1", 0);
+
+        assert_eq!(emu.get_display_planes()[..5], [1, 2, 1, 3, 0]);
+    }
+
+    #[test]
+    fn loads_audio_patterns() {
+        let pattern: [u8; AUDIO_PATTERN_SIZE] = array::from_fn(|idx| idx as u8 * 0x11);
+        let bytes: Vec<_> = pattern.iter().map(|byte| format!("{byte:#04X}")).collect();
+        let src = format!(
+            "
+            LD I, pattern
+            AUDIO
+            JP end
+            pattern: DB {}
+            end:
+            ",
+            bytes.join(", ")
+        );
+
+        assert_eq!(run_xo_chip(&src, 1).audio_pattern(), None);
+        assert_eq!(run_xo_chip(&src, 2).audio_pattern(), Some(pattern));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn sets_the_audio_pitch() {
+        assert_eq!(run_xo_chip("", 0).playback_rate(), 4000.0);
+
+        // Every 48 steps of pitch is an octave
+        let emu = run_xo_chip("LD V0, 112\nPITCH V0", 2);
+        assert_eq!(emu.playback_rate(), 8000.0);
+
+        let emu = run_xo_chip("LD V0, 16\nPITCH V0", 2);
+        assert_eq!(emu.playback_rate(), 2000.0);
+    }
+
+    #[test]
+    fn rejects_roms_too_large_for_ram() {
+        let max = RAM_SIZE - START_ADDR as usize;
+        let mut emu = Emu::new_with_seed(0);
+
+        assert_eq!(emu.load(&vec![0xFF; max]), Ok(()));
+        assert_eq!(
+            emu.load(&vec![0x12; max + 1]),
+            Err(EmuError::RomTooLarge { size: max + 1, max })
+        );
+
+        // The ROM that fit is left as it was
+        assert!(
+            emu.memory()[START_ADDR as usize..]
+                .iter()
+                .all(|&byte| byte == 0xFF)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn loads_from_readers() {
+        let rom = assemble("LD V0, 7\nLD V1, 8").unwrap();
+        let mut emu = Emu::new_with_seed(0);
+        emu.load_from(io::Cursor::new(&rom)).unwrap();
+
+        assert_eq!(
+            emu.memory()[START_ADDR as usize..START_ADDR as usize + 4],
+            rom
+        );
+
+        tick_n(&mut emu, 2);
+        assert_eq!(emu.registers()[..2], [7, 8]);
+
+        // Read errors come back as they are
+        struct Unplugged;
+
+        impl Read for Unplugged {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+        }
+
+        assert_eq!(
+            emu.load_from(Unplugged),
+            Err(EmuError::ReadFailed(io::ErrorKind::BrokenPipe))
+        );
+    }
+
+    #[test]
+    fn loads_at_a_custom_start_address() {
+        // The ETI 660's
+        let mut emu = Emu::new_with_config(EmuConfig {
+            start_addr: 0x600,
+            ..Default::default()
+        });
+        let rom = assemble("LD V0, 7\nLD V1, 8").unwrap();
+        emu.load(&rom).unwrap();
+
+        assert_eq!(emu.program_counter(), 0x600);
+        assert_eq!(emu.memory()[0x600..0x604], rom);
+        assert!(
+            emu.memory()[START_ADDR as usize..0x600]
+                .iter()
+                .all(|&byte| byte == 0)
+        );
+
+        tick_n(&mut emu, 2);
+        assert_eq!(emu.registers()[..2], [7, 8]);
+        assert_eq!(emu.program_counter(), 0x604);
+
+        assert_eq!(
+            emu.load(&[0; RAM_SIZE - 0x600 + 1]),
+            Err(EmuError::RomTooLarge {
+                size: RAM_SIZE - 0x600 + 1,
+                max: RAM_SIZE - 0x600
+            })
+        );
+    }
+
+    #[test]
+    fn picks_ram_for_quirks() {
+        assert_eq!(
+            EmuConfig::for_quirks(Quirks::xochip()),
+            EmuConfig::xo_chip()
+        );
+        assert_eq!(EmuConfig::xo_chip().quirks, Quirks::xochip());
+
+        for quirks in [Quirks::cowgod(), Quirks::chip8(), Quirks::schip()] {
+            assert_eq!(EmuConfig::for_quirks(quirks).ram_size, RAM_SIZE);
+        }
+
+        let mut emu = run("LD V0, 7\nLD I, 0xFFF\nLD [I], V0", 3);
+        emu.grow_ram(0x10000);
+
+        assert_eq!(emu.memory().len(), 0x10000);
+        assert_eq!(emu.memory()[0xFFF], 7);
+        assert_eq!(emu.peek(0x200), Ok(0x60));
+
+        // Never shrinks
+        emu.grow_ram(RAM_SIZE);
+        assert_eq!(emu.memory().len(), 0x10000);
     }
 }
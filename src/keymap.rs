@@ -0,0 +1,250 @@
+use std::{collections::HashMap, fs, io, mem, path::Path};
+
+use chip8_emu::constants::NUM_KEYS;
+use sdl2::{controller::Button, keyboard::Keycode};
+
+// QWERTY layout, with the arrow keys doubling up for 5/7/8/9:
+// 1 2 3 4        1 2 3 C
+// Q W E R   ->   4 5 6 D
+// A S D F        7 8 9 E
+// Z X C V        A 0 B F
+const DEFAULT_KEYS: [(Keycode, usize); 20] = [
+    (Keycode::Num1, 0x1),
+    (Keycode::Num2, 0x2),
+    (Keycode::Num3, 0x3),
+    (Keycode::Num4, 0xC),
+    (Keycode::Q, 0x4),
+    (Keycode::W, 0x5),
+    (Keycode::Up, 0x5),
+    (Keycode::E, 0x6),
+    (Keycode::R, 0xD),
+    (Keycode::A, 0x7),
+    (Keycode::Left, 0x7),
+    (Keycode::S, 0x8),
+    (Keycode::Down, 0x8),
+    (Keycode::D, 0x9),
+    (Keycode::Right, 0x9),
+    (Keycode::F, 0xE),
+    (Keycode::Z, 0xA),
+    (Keycode::X, 0x0),
+    (Keycode::C, 0xB),
+    (Keycode::V, 0xF),
+];
+
+// The D-pad moves like the arrow keys, with A and B on the buttons either side of 5
+const DEFAULT_PAD_BUTTONS: [(Button, usize); 6] = [
+    (Button::DPadUp, 0x5),
+    (Button::DPadLeft, 0x7),
+    (Button::DPadDown, 0x8),
+    (Button::DPadRight, 0x9),
+    (Button::A, 0x6),
+    (Button::B, 0x4),
+];
+
+// Something physical that can be mapped to a CHIP-8 button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Input {
+    Key(Keycode),
+    Pad(Button),
+}
+
+// Keyboard key/controller button -> CHIP-8 button
+pub struct Keymap {
+    buttons: HashMap<Input, usize>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let keys = DEFAULT_KEYS.map(|(key, button)| (Input::Key(key), button));
+        let pad_buttons =
+            DEFAULT_PAD_BUTTONS.map(|(pad_button, button)| (Input::Pad(pad_button), button));
+
+        Self {
+            buttons: keys.into_iter().chain(pad_buttons).collect(),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn button(&self, key: Keycode) -> Option<usize> {
+        self.input_button(Input::Key(key))
+    }
+
+    pub fn input_button(&self, input: Input) -> Option<usize> {
+        self.buttons.get(&input).copied()
+    }
+
+    // Missing files aren't an error, there's just nothing to override the defaults with
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(config) => Self::parse(&config).map_err(|err| format!("{}: {err}", path.display())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(format!("{}: {err}", path.display())),
+        }
+    }
+
+    // Parses a TOML style list of `key = button` pairs, using SDL's key names, or SDL's
+    // controller button names after "pad":
+    //
+    //   # AZERTY
+    //   A = 0x4
+    //   Z = 0x5
+    //   "Keypad 8" = 5
+    //   "pad x" = 0xC
+    //
+    // Buttons mentioned in the config lose their default keys (or controller buttons, for
+    // controller mappings). Any others keep them.
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let mut overrides = HashMap::new();
+
+        for (line_idx, line) in config.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let err = |msg: &str| format!("line {}: {msg}", line_idx + 1);
+
+            let (name, button) = line
+                .split_once('=')
+                .ok_or_else(|| err("expected key = button"))?;
+
+            let name = name.trim().trim_matches('"');
+            let input = match name.strip_prefix("pad ") {
+                Some(pad_name) => Button::from_string(pad_name).map(Input::Pad),
+                None => Keycode::from_name(name).map(Input::Key),
+            }
+            .ok_or_else(|| err(&format!("unknown key {name}")))?;
+
+            // Allow trailing comments after the button
+            let button = button.split('#').next().unwrap().trim();
+            let button = match button.strip_prefix("0x") {
+                Some(hex) => usize::from_str_radix(hex, 16),
+                None => button.parse(),
+            };
+
+            match button {
+                Ok(button) if button < NUM_KEYS => overrides.insert(input, button),
+                _ => return Err(err("button must be between 0x0 and 0xF")),
+            };
+        }
+
+        let mut keymap = Self::default();
+
+        keymap.buttons.retain(|input, button| {
+            !overrides.iter().any(|(overridden_input, overridden)| {
+                overridden == button
+                    && mem::discriminant(overridden_input) == mem::discriminant(input)
+            })
+        });
+        keymap.buttons.extend(overrides);
+
+        Ok(keymap)
+    }
+}
+
+// Merges keyboard and controller input, so a CHIP-8 button mapped to several inputs stays
+// pressed until the last of them is let go
+pub struct Keypad {
+    keymap: Keymap,
+    held: HashMap<Input, usize>,
+}
+
+impl Keypad {
+    pub fn new(keymap: Keymap) -> Self {
+        Self {
+            keymap,
+            held: HashMap::new(),
+        }
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    // Returns the CHIP-8 button the input maps to, and whether that button is now pressed
+    pub fn set(&mut self, input: Input, pressed: bool) -> Option<(usize, bool)> {
+        let button = self.keymap.input_button(input)?;
+
+        if pressed {
+            self.held.insert(input, button);
+        } else {
+            self.held.remove(&input);
+        }
+
+        Some((button, self.held.values().any(|&held| held == button)))
+    }
+
+    // Lets go of every controller button, returning the same as `set` for each one released
+    pub fn release_pad(&mut self) -> Vec<(usize, bool)> {
+        let pad_inputs: Vec<_> = self
+            .held
+            .keys()
+            .filter(|input| matches!(input, Input::Pad(_)))
+            .copied()
+            .collect();
+
+        pad_inputs
+            .into_iter()
+            .filter_map(|input| self.set(input, false))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_configs() {
+        let keymap = Keymap::parse(
+            "
+            # AZERTY
+            A = 0x4
+            Z = 5  # instead of W
+            \"Keypad 8\" = 0x8
+            \"pad x\" = 0xC
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(keymap.button(Keycode::A), Some(0x4));
+        assert_eq!(keymap.button(Keycode::Z), Some(0x5));
+        assert_eq!(keymap.button(Keycode::Kp8), Some(0x8));
+        assert_eq!(keymap.input_button(Input::Pad(Button::X)), Some(0xC));
+
+        // Remapped buttons lose their default keys, but not their controller buttons
+        assert_eq!(keymap.button(Keycode::Q), None);
+        assert_eq!(keymap.button(Keycode::W), None);
+        assert_eq!(keymap.button(Keycode::Up), None);
+        assert_eq!(keymap.input_button(Input::Pad(Button::DPadUp)), Some(0x5));
+
+        // Others keep theirs
+        assert_eq!(keymap.button(Keycode::X), Some(0x0));
+        assert_eq!(keymap.button(Keycode::Num4), Some(0xC));
+    }
+
+    #[test]
+    fn rejects_bad_configs() {
+        let errors = [
+            ("A 4", "line 1: expected key = button"),
+            ("\nNotAKey = 4", "line 2: unknown key NotAKey"),
+            ("\"pad nope\" = 4", "line 1: unknown key pad nope"),
+            ("A = 0x10", "line 1: button must be between 0x0 and 0xF"),
+            ("A = four", "line 1: button must be between 0x0 and 0xF"),
+        ];
+
+        for (config, err) in errors {
+            assert_eq!(Keymap::parse(config).err().as_deref(), Some(err));
+        }
+    }
+
+    #[test]
+    fn defaults_without_a_config() {
+        let keymap = Keymap::load(Path::new("no/such/keymap.toml")).unwrap();
+
+        assert_eq!(keymap.button(Keycode::Q), Some(0x4));
+        assert_eq!(keymap.button(Keycode::Space), None);
+    }
+}
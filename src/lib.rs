@@ -0,0 +1,20 @@
+// Without the std feature, the core only needs an allocator
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod asm;
+pub mod constants;
+pub mod debugger;
+pub mod disasm;
+pub mod emu;
+pub mod quirks;
+pub mod render;
+mod rewind;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use emu::{Emu, EmuConfig, EmuError};
+pub use quirks::Quirks;
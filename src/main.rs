@@ -1,46 +1,326 @@
-use std::{env, fs, io, process::ExitCode};
+use std::{
+    env, error,
+    fs::{self, File},
+    io::{self, BufWriter, IsTerminal, Read},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use constants::{SCALE, SCREEN_WIDTH, TICKS_PER_FRAME, WINDOW_HEIGHT, WINDOW_WIDTH};
-use emu::Emu;
-use sdl2::{event::Event, keyboard::Keycode, pixels::Color, rect::Rect, render};
+use args::{Args, Mode};
+use base64::prelude::{BASE64_STANDARD, Engine};
+use chip8_emu::{
+    constants::{DEFAULT_REWIND_DEPTH, SCREEN_HEIGHT, SCREEN_WIDTH, TIMER_FREQ, WINDOW_TITLE},
+    disasm,
+    emu::{CodeWrite, Emu, EmuConfig, EmuError},
+    quirks::Quirks,
+};
+use keymap::{Input, Keymap, Keypad};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use recorder::GifRecorder;
+use replay::{InputEvent, InputPlayer, InputRecorder};
+use sdl_renderer::SdlRenderer;
+use sdl2::{
+    event::{Event, WindowEvent},
+    keyboard::{Keycode, Mod},
+    render,
+    video::FullscreenType,
+};
 
-mod constants;
-mod emu;
+mod archive;
+mod args;
+mod audio;
+mod demos;
+mod keymap;
+mod memview;
+mod net;
+mod overlay;
+mod palette;
+mod recorder;
+mod repl;
+mod replay;
+mod romdb;
+mod screenshot;
+mod sdl_renderer;
+mod terminal;
+
+const TITLE_MESSAGE_TIME: Duration = Duration::from_secs(2);
+
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / TIMER_FREQ as u64);
+
+// Frames run per frame while Tab is held
+const FAST_FORWARD_FRAMES: u32 = 5;
+
+// How often the ROM file is checked for changes
+const ROM_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// How long to sleep between checks for events while the window is unfocused, or waiting for a
+// game
+const IDLE_SLEEP: Duration = Duration::from_millis(50);
+
+// Frames owed after a stall (e.g. the window being dragged) are dropped past this point
+// rather than all run at once
+const MAX_FRAMES_BEHIND: u32 = 5;
 
 fn main() -> ExitCode {
-    let args: Vec<_> = env::args().collect();
+    let args = match args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}");
+            println!("{}", args::USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
 
-    if args.len() != 2 {
-        println!("Usage: cargo run path/to/game");
-        return ExitCode::FAILURE;
+    if args.mode == Mode::Disasm {
+        return print_disassembly(&args);
+    }
+
+    if args.mode == Mode::Check {
+        return check_rom(&args);
+    }
+
+    if args.mode == Mode::ListDemos {
+        for (name, description, _) in demos::DEMOS {
+            println!("{name:<8}  {description}");
+        }
+
+        return ExitCode::SUCCESS;
+    }
+
+    let keymap = match Keymap::load(Path::new(&args.keymap_path)) {
+        Ok(keymap) => keymap,
+        Err(err) => {
+            eprintln!("Invalid keymap {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let emu = if args.rom_path.is_empty() {
+        Ok(Emu::new_with_config(with_quirk_flags(
+            EmuConfig::for_quirks(args.quirks.unwrap_or_default()),
+            &args,
+        )))
+    } else {
+        create_and_load_emulator(&args)
+    };
+
+    let mut emu = match emu {
+        Ok(emu) => emu,
+        Err(err) => {
+            eprintln!("Unable to load emulator file! {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut input_player = match &args.replay_path {
+        Some(replay_path) => match InputPlayer::load(Path::new(replay_path)) {
+            Ok(input_player) => Some(input_player),
+            Err(err) => {
+                eprintln!("Invalid input log {err}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+
+    // Instructions per frame, which +/- adjust. A replay runs at the speed and with the seed it
+    // was recorded with. Recording writes the seed down, so without --seed it picks one.
+    let mut speed = input_player
+        .as_ref()
+        .and_then(InputPlayer::speed)
+        .unwrap_or(args.speed);
+    let seed = input_player
+        .as_ref()
+        .and_then(InputPlayer::seed)
+        .or(args.seed)
+        .or_else(|| {
+            args.record_path
+                .is_some()
+                .then(|| StdRng::from_os_rng().random())
+        });
+
+    emu.set_ticks_per_frame(speed);
+    emu.set_idle_skip(args.idle_sleep);
+    emu.set_coverage(args.profile);
+    emu.set_hotspots(args.hotspots > 0);
+    emu.set_smc_check(args.warn_smc);
+
+    if let Some(seed) = seed {
+        emu.set_seed(seed);
+    }
+
+    if let Some(trace_path) = &args.trace_path {
+        match File::create(trace_path) {
+            Ok(file) => emu.set_trace(Box::new(BufWriter::new(file))),
+            Err(err) => {
+                eprintln!("Unable to create {trace_path}: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if args.mode == Mode::Terminal {
+        let exit_code = terminal::run(&mut emu, &keymap);
+        // Printing while the terminal is being drawn in would garble it, so it waits till the end
+        warn_code_writes(&mut emu);
+        print_profile(&emu, &args);
+
+        return exit_code;
     }
 
+    // For Backspace
+    emu.set_rewind_depth(DEFAULT_REWIND_DEPTH);
+
+    let mut input_recorder = match (&args.record_path, seed) {
+        (Some(record_path), Some(seed)) => {
+            match InputRecorder::create(Path::new(record_path), seed, speed) {
+                Ok(input_recorder) => Some(input_recorder),
+                Err(err) => {
+                    eprintln!("Unable to create {record_path}: {err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        _ => None,
+    };
+
     // TODO: more robust error handling
 
     let sdl_context = sdl2::init().unwrap();
     let video_subsytem = sdl_context.video().unwrap();
 
     let window = video_subsytem
-        .window("CHIP-8 Emulator", WINDOW_WIDTH, WINDOW_HEIGHT)
+        .window(
+            WINDOW_TITLE,
+            SCREEN_WIDTH as u32 * args.scale,
+            SCREEN_HEIGHT as u32 * args.scale,
+        )
         .position_centered()
+        .resizable()
         .opengl()
         .build()
         .unwrap();
 
-    let mut canvas = window.into_canvas().present_vsync().build().unwrap();
+    let mut canvas_builder = window.into_canvas();
+
+    if args.vsync {
+        canvas_builder = canvas_builder.present_vsync();
+    }
+
+    let mut canvas = canvas_builder.build().unwrap();
 
     canvas.clear();
     canvas.present();
 
+    let mut renderer = SdlRenderer::new(canvas, args.palette);
+    renderer.fade_frames = args.fade;
+
     let mut event_pump = sdl_context.event_pump().unwrap();
 
-    let mut emu = match create_and_load_emulator(&args[1]) {
-        Ok(emu) => emu,
-        Err(_) => {
-            eprintln!("Unable to load emulator file!");
-            return ExitCode::FAILURE;
-        }
-    };
+    // Controllers are opened as they're connected. SDL reports ones that are already plugged in
+    // as being connected at startup.
+    let game_controller_subsystem = sdl_context.game_controller().unwrap();
+    let mut controllers = Vec::new();
+
+    let audio_subsystem = sdl_context.audio().unwrap();
+    let mut beeper = audio::open_beeper(&audio_subsystem, args.waveform, args.beep_freq).unwrap();
+
+    if args.mode == Mode::Debug {
+        let exit_code = repl::run(
+            &mut emu,
+            &mut renderer,
+            &mut event_pump,
+            &keymap,
+            args.break_on_unknown,
+        );
+        print_profile(&emu, &args);
+
+        return exit_code;
+    }
+
+    let mut keypad = Keypad::new(keymap);
+
+    // Dropping a file onto the window swaps it in as the game. Until there's a game, nothing
+    // runs.
+    let mut rom_path = args.rom_path.clone();
+
+    // F6/F7 save/restore to memory, F8/F9 to a .state file next to the ROM
+    let mut quick_save: Option<Vec<u8>> = None;
+    let mut state_path = Path::new(&rom_path).with_extension("state");
+
+    // F3 reloads the ROM from disk, as does saving over it. Demos, ROMs piped through stdin,
+    // base64 ones and downloads have no file to reload from.
+    let mut rom_file = (args.demo.is_none()
+        && args.base64.is_none()
+        && args.url.is_none()
+        && !rom_path.is_empty()
+        && rom_path != "-")
+        .then(|| PathBuf::from(&rom_path));
+    let mut rom_modified = rom_file.as_deref().and_then(modified_time);
+    let mut rom_checked_at = Instant::now();
+    let mut reload = false;
+
+    // P pauses, then N/M advance a single instruction/frame. --start-paused starts out paused,
+    // before the first instruction.
+    let mut paused = args.start_paused;
+    let mut step_tick = false;
+    let mut step_frame = false;
+
+    // F10 starts/stops recording a GIF
+    let mut recorder: Option<GifRecorder> = None;
+
+    // Backspace rewinds one frame per frame for as long as it's held
+    let mut rewinding = false;
+
+    // Tab runs several frames per frame, timers included, for as long as it's held
+    let mut fast_forwarding = false;
+
+    // [/] shrink/grow the window, which can also be resized by dragging its edges
+    let mut scale = args.scale;
+
+    // F11 toggles fullscreen
+    let mut fullscreen = false;
+
+    // F1 shows the registers and timers over the display
+    let mut show_overlay = false;
+
+    // O shows the sprite I points at, and where the DXYN at the PC would draw it
+    let mut show_sprite = false;
+
+    // Emulation stops while another window has focus, unless --no-pause-unfocused is given
+    let mut focused = true;
+
+    // Messages shown in the title bar go back to the plain title after a while
+    let mut title_reset_at: Option<Instant> = None;
+
+    // Once a second the title shows the frames drawn and instructions run over the last second
+    let mut title = WINDOW_TITLE.to_string();
+    let mut stats_started = Instant::now();
+    let mut frames_drawn = 0;
+    let mut cycles_run = 0;
+
+    // Frames run so far, which recorded input is keyed on
+    let mut frame: u64 = 0;
+
+    // Frames run off the wall clock rather than once per loop, so the timers keep to 60Hz
+    // whatever rate frames are drawn at
+    let mut last_frame = Instant::now();
+    let mut lag = Duration::ZERO;
+
+    // Without vsync, drawing is held to the target FPS by sleeping instead
+    let draw_time = (!args.vsync).then(|| Duration::from_nanos(1_000_000_000 / args.fps as u64));
+    let mut next_draw = Instant::now();
+
+    let mut exit_code = ExitCode::SUCCESS;
+
+    // With --break-on-unknown, an unknown opcode leaves the loop for the debugger prompt
+    let mut unknown_opcode = None;
+
+    // Input logs only have the keypad in them, so anything else that changes how the game plays
+    // out is turned off while recording or replaying one
+    let input_locked = input_recorder.is_some() || input_player.is_some();
 
     'gameloop: loop {
         for event in event_pump.poll_iter() {
@@ -48,6 +328,16 @@ fn main() -> ExitCode {
                 Event::Quit { .. } => {
                     break 'gameloop;
                 }
+                Event::KeyDown {
+                    keycode: Some(key), ..
+                } if input_locked && changes_run(key, paused) => {
+                    show_title_message(&mut renderer.canvas, "Not while recording or replaying");
+                    title_reset_at = Some(Instant::now() + TITLE_MESSAGE_TIME);
+                }
+                Event::DropFile { .. } if input_locked => {
+                    show_title_message(&mut renderer.canvas, "Not while recording or replaying");
+                    title_reset_at = Some(Instant::now() + TITLE_MESSAGE_TIME);
+                }
                 Event::KeyDown {
                     keycode: Some(Keycode::Escape),
                     ..
@@ -55,84 +345,837 @@ fn main() -> ExitCode {
                     break 'gameloop;
                 }
                 Event::KeyDown {
-                    keycode: Some(key), ..
+                    keycode: Some(Keycode::Equals | Keycode::Plus | Keycode::KpPlus),
+                    ..
+                } => {
+                    speed += 1;
+                    emu.set_ticks_per_frame(speed);
+                    show_title_message(&mut renderer.canvas, &format!("Speed: {speed}"));
+                    title_reset_at = Some(Instant::now() + TITLE_MESSAGE_TIME);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Minus | Keycode::KpMinus),
+                    ..
+                } => {
+                    speed = (speed - 1).max(1);
+                    emu.set_ticks_per_frame(speed);
+                    show_title_message(&mut renderer.canvas, &format!("Speed: {speed}"));
+                    title_reset_at = Some(Instant::now() + TITLE_MESSAGE_TIME);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::LeftBracket),
+                    ..
                 } => {
-                    if let Some(k) = key_to_button(key) {
-                        emu.keypress(k as usize, true);
+                    scale = (scale - 1).max(1);
+                    resize_window(&mut renderer.canvas, scale);
+                    show_title_message(&mut renderer.canvas, &format!("Scale: {scale}"));
+                    title_reset_at = Some(Instant::now() + TITLE_MESSAGE_TIME);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::RightBracket),
+                    ..
+                } => {
+                    scale += 1;
+                    resize_window(&mut renderer.canvas, scale);
+                    show_title_message(&mut renderer.canvas, &format!("Scale: {scale}"));
+                    title_reset_at = Some(Instant::now() + TITLE_MESSAGE_TIME);
+                }
+                Event::Window {
+                    win_event: WindowEvent::Resized(width, height),
+                    ..
+                } if !fullscreen => {
+                    // So [/] carry on from the closest scale to the new size
+                    scale = (width as u32 / SCREEN_WIDTH as u32)
+                        .min(height as u32 / SCREEN_HEIGHT as u32)
+                        .max(1);
+                }
+                Event::Window {
+                    win_event: WindowEvent::FocusLost,
+                    ..
+                } if args.pause_unfocused => {
+                    focused = false;
+                }
+                Event::Window {
+                    win_event: WindowEvent::FocusGained,
+                    ..
+                } => {
+                    focused = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F1),
+                    ..
+                } => {
+                    show_overlay = !show_overlay;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::K),
+                    ..
+                } => {
+                    // Onto the next preset, or the first if they're not one
+                    let current = emu.config().quirks.preset_name();
+                    let next = Quirks::PRESETS
+                        .iter()
+                        .position(|&name| Some(name) == current)
+                        .map_or(0, |idx| (idx + 1) % Quirks::PRESETS.len());
+                    let name = Quirks::PRESETS[next];
+                    let quirks = Quirks::preset(name).unwrap();
+
+                    // Switching to XO-CHIP's brings its 64 KB of RAM with it. States saved
+                    // before then are the wrong size to load, so the quick save goes along
+                    // with the rewind history `grow_ram` drops.
+                    let ram_size = emu.memory().len();
+                    emu.set_quirks(quirks);
+                    emu.grow_ram(EmuConfig::for_quirks(quirks).ram_size);
+
+                    if emu.memory().len() != ram_size {
+                        quick_save = None;
                     }
+
+                    show_title_message(&mut renderer.canvas, &format!("Quirks: {name}"));
+                    title_reset_at = Some(Instant::now() + TITLE_MESSAGE_TIME);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::G),
+                    ..
+                } => {
+                    renderer.grid = !renderer.grid;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::O),
+                    ..
+                } => {
+                    show_sprite = !show_sprite;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F2),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                    print!("{}", emu.debug_dump());
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F2),
+                    ..
+                } => {
+                    let ansi = io::stdout().is_terminal();
+                    let ram_size = emu.memory().len();
+                    print!("{}", memview::hex_dump(&emu, 0, ram_size, ansi));
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    ..
+                } => {
+                    fullscreen = !fullscreen;
+                    set_fullscreen(&mut renderer.canvas, fullscreen, scale);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::P),
+                    ..
+                } => {
+                    paused = !paused;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::I),
+                    ..
+                } => {
+                    renderer.inverted = !renderer.inverted;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::N),
+                    ..
+                } if paused => {
+                    step_tick = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::M),
+                    ..
+                } if paused => {
+                    step_frame = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    ..
+                } => {
+                    let path = capture_path(&rom_path, "png");
+
+                    match screenshot::save(&emu, renderer.colors(), &path) {
+                        Ok(()) => {
+                            show_title_message(&mut renderer.canvas, "Saved screenshot");
+                            title_reset_at = Some(Instant::now() + TITLE_MESSAGE_TIME);
+                        }
+                        Err(err) => eprintln!("Unable to write {}: {err}", path.display()),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F10),
+                    ..
+                } => match recorder.take() {
+                    Some(recording) => {
+                        finish_recording(recording, &rom_path);
+                    }
+                    None => {
+                        recorder = Some(GifRecorder::start(renderer.colors()));
+                        show_title_message(&mut renderer.canvas, "Recording");
+                        title_reset_at = Some(Instant::now() + TITLE_MESSAGE_TIME);
+                    }
+                },
+                Event::KeyDown {
+                    keycode: Some(Keycode::Backspace),
+                    ..
+                } => {
+                    rewinding = true;
                 }
                 Event::KeyUp {
-                    keycode: Some(key), ..
+                    keycode: Some(Keycode::Backspace),
+                    ..
+                } => {
+                    rewinding = false;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Tab),
+                    ..
+                } => {
+                    fast_forwarding = true;
+                }
+                Event::KeyUp {
+                    keycode: Some(Keycode::Tab),
+                    ..
+                } => {
+                    fast_forwarding = false;
+                }
+                Event::DropFile { filename, .. } => {
+                    let path = PathBuf::from(&filename);
+
+                    match load_rom_file(&mut emu, &path, &args) {
+                        Ok(()) => {
+                            rom_path = filename;
+                            state_path = path.with_extension("state");
+                            rom_modified = modified_time(&path);
+                            rom_file = Some(path);
+                            quick_save = None;
+                        }
+                        Err(err) => {
+                            eprintln!("Unable to load {filename}: {err}");
+                            show_title_message(&mut renderer.canvas, "Unable to load that file");
+                            title_reset_at = Some(Instant::now() + TITLE_MESSAGE_TIME);
+                        }
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    ..
+                } => {
+                    reload = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F4),
+                    ..
+                } => {
+                    let mut tone = beeper.lock();
+                    let muted = !tone.is_muted();
+                    tone.set_muted(muted);
+
+                    show_title_message(
+                        &mut renderer.canvas,
+                        if muted { "Muted" } else { "Unmuted" },
+                    );
+                    title_reset_at = Some(Instant::now() + TITLE_MESSAGE_TIME);
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::Comma | Keycode::Period)),
+                    ..
                 } => {
-                    if let Some(k) = key_to_button(key) {
-                        emu.keypress(k as usize, false);
+                    let mut tone = beeper.lock();
+                    let step = if keycode == Keycode::Period {
+                        audio::VOLUME_STEP
+                    } else {
+                        -audio::VOLUME_STEP
+                    };
+                    let volume = tone.volume() + step;
+                    tone.set_volume(volume);
+
+                    let message = format!("Volume: {:.0}%", tone.volume() * 100.0);
+                    show_title_message(&mut renderer.canvas, &message);
+                    title_reset_at = Some(Instant::now() + TITLE_MESSAGE_TIME);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F5),
+                    ..
+                } => {
+                    emu.reset();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F6),
+                    ..
+                } => {
+                    quick_save = Some(emu.save_state());
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F7),
+                    ..
+                } => {
+                    // Nothing to restore until something has been saved
+                    if let Some(state) = &quick_save
+                        && let Err(err) = emu.load_state(state)
+                    {
+                        eprintln!("Unable to load the quick save: {err}");
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F8),
+                    ..
+                } => {
+                    if let Err(err) = fs::write(&state_path, emu.save_state()) {
+                        eprintln!("Unable to write {}: {err}", state_path.display());
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9),
+                    ..
+                } => {
+                    // A missing state file just means nothing has been saved yet
+                    if let Ok(state) = fs::read(&state_path)
+                        && let Err(err) = emu.load_state(&state)
+                    {
+                        eprintln!("Unable to load {}: {err}", state_path.display());
+                    }
+                }
+                Event::ControllerDeviceAdded { which, .. } => {
+                    match game_controller_subsystem.open(which) {
+                        Ok(controller) => controllers.push(controller),
+                        Err(err) => eprintln!("Unable to open controller: {err}"),
+                    }
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    controllers.retain(|controller| controller.instance_id() != which);
+
+                    for (k, pressed) in keypad.release_pad() {
+                        emu.keypress(k, pressed);
+                        record_input(&mut input_recorder, frame, k, pressed);
+                    }
+                }
+                // The keyboard and controllers are ignored during a replay so the run plays out
+                // as recorded
+                Event::KeyDown {
+                    keycode: Some(key), ..
+                }
+                | Event::KeyUp {
+                    keycode: Some(key), ..
+                } if input_player.is_none() => {
+                    let pressed = matches!(event, Event::KeyDown { .. });
+
+                    if let Some((k, pressed)) = keypad.set(Input::Key(key), pressed) {
+                        emu.keypress(k, pressed);
+                        record_input(&mut input_recorder, frame, k, pressed);
+                    }
+                }
+                Event::ControllerButtonDown { button, .. }
+                | Event::ControllerButtonUp { button, .. }
+                    if input_player.is_none() =>
+                {
+                    let pressed = matches!(event, Event::ControllerButtonDown { .. });
+
+                    if let Some((k, pressed)) = keypad.set(Input::Pad(button), pressed) {
+                        emu.keypress(k, pressed);
+                        record_input(&mut input_recorder, frame, k, pressed);
                     }
                 }
                 _ => (),
             }
         }
 
-        for _ in 0..TICKS_PER_FRAME {
-            emu.tick();
+        let now = Instant::now();
+        lag = (lag + (now - last_frame)).min(FRAME_TIME * MAX_FRAMES_BEHIND);
+        last_frame = now;
+
+        if let Some(rom_file) = &rom_file
+            && !input_locked
+            && now - rom_checked_at >= ROM_CHECK_INTERVAL
+        {
+            rom_checked_at = now;
+
+            let modified = modified_time(rom_file);
+
+            if modified.is_some() && modified != rom_modified {
+                rom_modified = modified;
+                reload = true;
+            }
+        }
+
+        if reload && let Some(rom_file) = &rom_file {
+            match load_rom_file(&mut emu, rom_file, &args) {
+                Ok(()) => {
+                    // The ROM may have changed on disk, so a state saved from the old one
+                    // could be for a different program
+                    quick_save = None;
+                    show_title_message(&mut renderer.canvas, "Reloaded");
+                    title_reset_at = Some(Instant::now() + TITLE_MESSAGE_TIME);
+                }
+                Err(err) => eprintln!("Unable to reload {}: {err}", rom_file.display()),
+            }
         }
 
-        emu.tick_timers();
-        draw_screen(&emu, &mut canvas);
+        reload = false;
+
+        let mut result = Ok(());
+
+        if rom_path.is_empty() || !focused {
+            // Nothing to catch up on once there's a game or focus comes back, and no point
+            // spinning until then
+            lag = Duration::ZERO;
+            thread::sleep(IDLE_SLEEP);
+        } else if rewinding || !paused {
+            while lag >= FRAME_TIME && result.is_ok() {
+                lag -= FRAME_TIME;
+
+                let frames = if fast_forwarding && !rewinding {
+                    FAST_FORWARD_FRAMES
+                } else {
+                    1
+                };
+
+                for _ in 0..frames {
+                    if rewinding {
+                        emu.rewind();
+                    } else {
+                        result = run_frame(&mut emu, &mut input_player, &mut frame);
+                        cycles_run += speed;
+                    }
+
+                    if let Some(recording) = &mut recorder {
+                        recording.push_frame(&emu);
+                    }
+
+                    if result.is_err() {
+                        break;
+                    }
+                }
+            }
+        } else {
+            // Nothing builds up while paused, stepping goes at its own pace
+            lag = Duration::ZERO;
+
+            if step_frame {
+                result = run_frame(&mut emu, &mut input_player, &mut frame);
+                cycles_run += speed;
+            } else if step_tick {
+                result = emu.tick();
+                cycles_run += 1;
+            }
+        }
+
+        step_tick = false;
+        step_frame = false;
+
+        match result {
+            Err(EmuError::UnknownOpcode(op)) if args.break_on_unknown => {
+                unknown_opcode = Some(op);
+                break 'gameloop;
+            }
+            Err(err) => {
+                eprintln!("Emulation stopped: {err}");
+                exit_code = ExitCode::FAILURE;
+                break 'gameloop;
+            }
+            Ok(()) => (),
+        }
+
+        warn_code_writes(&mut emu);
+
+        // Stop by itself rather than grow without bound
+        if recorder.as_ref().is_some_and(GifRecorder::is_full) {
+            finish_recording(recorder.take().unwrap(), &rom_path);
+        }
+
+        // Beeps would only be clicks at fast forward speed
+        if emu.is_beeping() && !paused && focused && !fast_forwarding {
+            beeper
+                .lock()
+                .set_pattern(emu.audio_pattern(), emu.playback_rate());
+            beeper.resume();
+        } else {
+            beeper.pause();
+        }
+
+        if now - stats_started >= Duration::from_secs(1) {
+            title = if rom_path.is_empty() {
+                format!("{WINDOW_TITLE} - Drop a game here")
+            } else {
+                let quirks = emu.config().quirks.preset_name().unwrap_or("custom");
+                format!("{WINDOW_TITLE} - {frames_drawn} FPS - {cycles_run} Hz - {quirks} quirks")
+            };
+            stats_started = now;
+            frames_drawn = 0;
+            cycles_run = 0;
+
+            if title_reset_at.is_none() {
+                renderer.canvas.window_mut().set_title(&title).unwrap();
+            }
+        }
+
+        if title_reset_at.is_some_and(|reset_at| Instant::now() >= reset_at) {
+            renderer.canvas.window_mut().set_title(&title).unwrap();
+            title_reset_at = None;
+        }
+
+        renderer.overlay = show_overlay.then(|| overlay::lines(&emu));
+        renderer.sprite = show_sprite.then(|| overlay::sprite(&emu));
+        emu.render(&mut renderer);
+        frames_drawn += 1;
+
+        if let Some(draw_time) = draw_time {
+            next_draw += draw_time;
+
+            match next_draw.checked_duration_since(Instant::now()) {
+                Some(wait) => thread::sleep(wait),
+                // Running behind, so start over from now rather than draw a burst to catch up
+                None => next_draw = Instant::now(),
+            }
+        }
     }
 
-    ExitCode::SUCCESS
+    if let Some(recording) = recorder {
+        finish_recording(recording, &rom_path);
+    }
+
+    if let Some(op) = unknown_opcode {
+        beeper.pause();
+        repl::stop_at_unknown(&mut emu, op);
+
+        exit_code = repl::run(
+            &mut emu,
+            &mut renderer,
+            &mut event_pump,
+            keypad.keymap(),
+            true,
+        );
+    }
+
+    print_profile(&emu, &args);
+
+    exit_code
 }
 
-fn draw_screen(emu: &Emu, canvas: &mut render::Canvas<sdl2::video::Window>) {
-    canvas.set_draw_color(Color::BLACK);
-    canvas.clear();
+fn run_frame(
+    emu: &mut Emu,
+    input_player: &mut Option<InputPlayer>,
+    frame: &mut u64,
+) -> Result<(), EmuError> {
+    if let Some(input_player) = input_player {
+        input_player.apply(*frame, emu);
+    }
+
+    *frame += 1;
+
+    emu.run_frame()
+}
+
+// Keys for restarting, loading states, rewinding, stepping, changing the speed or quirks, and
+// reloading the ROM. N and M only step while paused.
+fn changes_run(key: Keycode, paused: bool) -> bool {
+    match key {
+        Keycode::N | Keycode::M => paused,
+        Keycode::F3
+        | Keycode::F5
+        | Keycode::F7
+        | Keycode::F9
+        | Keycode::Backspace
+        | Keycode::K
+        | Keycode::Equals
+        | Keycode::Plus
+        | Keycode::KpPlus
+        | Keycode::Minus
+        | Keycode::KpMinus => true,
+        _ => false,
+    }
+}
+
+fn record_input(input_recorder: &mut Option<InputRecorder>, frame: u64, key: usize, pressed: bool) {
+    let Some(recorder) = input_recorder else {
+        return;
+    };
+
+    let event = InputEvent {
+        frame,
+        key,
+        pressed,
+    };
+
+    // Carry on playing if the log can't be written, just without recording
+    if let Err(err) = recorder.record(event) {
+        eprintln!("Unable to record input: {err}");
+        *input_recorder = None;
+    }
+}
+
+// Screenshots and recordings go next to the ROM, named after it and the time they were taken
+fn capture_path(rom_path: &str, extension: &str) -> PathBuf {
+    let rom_path = Path::new(rom_path);
+    let stem = rom_path.file_stem().unwrap_or_default().to_string_lossy();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    rom_path.with_file_name(format!("{stem}-{timestamp}.{extension}"))
+}
+
+fn finish_recording(recording: GifRecorder, rom_path: &str) {
+    let path = capture_path(rom_path, "gif");
+
+    match recording.finish(&path) {
+        Ok(()) => println!("Saved recording to {}", path.display()),
+        Err(err) => eprintln!("Unable to write {}: {err}", path.display()),
+    }
+}
+
+// The display is always drawn to fill the window, so this is all it takes to rescale it
+fn resize_window(canvas: &mut render::Canvas<sdl2::video::Window>, scale: u32) {
+    canvas
+        .window_mut()
+        .set_size(SCREEN_WIDTH as u32 * scale, SCREEN_HEIGHT as u32 * scale)
+        .unwrap();
+}
+
+// Fullscreen covers the desktop at its current resolution, without changing video modes
+fn set_fullscreen(canvas: &mut render::Canvas<sdl2::video::Window>, fullscreen: bool, scale: u32) {
+    let fullscreen_type = if fullscreen {
+        FullscreenType::Desktop
+    } else {
+        FullscreenType::Off
+    };
+
+    canvas.window_mut().set_fullscreen(fullscreen_type).unwrap();
+
+    if !fullscreen {
+        resize_window(canvas, scale);
+    }
+}
+
+// Swaps in a game from disk and restarts. On failure the current game is left as it was.
+// Picks the quirks for the ROM with --quirks auto, and the ROM in a zip with --entry
+fn load_rom_file(emu: &mut Emu, path: &Path, args: &Args) -> Result<(), Box<dyn error::Error>> {
+    let rom = if archive::is_zip(path) {
+        archive::read_rom(path, args.entry.as_deref(), false)?
+    } else {
+        fs::read(path)?
+    };
+
+    if args.quirks.is_none() {
+        let config = with_quirk_flags(detect_config(&rom), args);
+
+        emu.set_quirks(config.quirks);
+        emu.grow_ram(config.ram_size);
+    }
+
+    // Loading leaves the rest of the machine alone, and resetting keeps the program in RAM
+    emu.load(&rom)?;
+    emu.reset();
+
+    Ok(())
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn show_title_message(canvas: &mut render::Canvas<sdl2::video::Window>, message: &str) {
+    canvas
+        .window_mut()
+        .set_title(&format!("{WINDOW_TITLE} - {message}"))
+        .unwrap();
+}
+
+// Prints nothing unless --warn-smc turned the check on
+fn warn_code_writes(emu: &mut Emu) {
+    for CodeWrite { pc, write } in emu.take_code_writes() {
+        eprintln!(
+            "Self-modifying code: {pc:#06X} wrote {:#04X} over {:#04X} at {:#06X}, which has \
+             already run",
+            write.new, write.old, write.addr
+        );
+    }
+}
+
+// Prints nothing unless --profile or --hotspots turned counting on
+fn print_profile(emu: &Emu, args: &Args) {
+    for (family, count) in emu.coverage_report() {
+        println!("{family:<16}  {count}");
+    }
+
+    for (pc, count) in emu.hotspots(args.hotspots) {
+        let ram = emu.memory();
+        let op = u16::from_be_bytes([
+            ram[pc as usize % ram.len()],
+            ram[(pc as usize + 1) % ram.len()],
+        ]);
 
-    let screen_buf = emu.get_display();
+        println!("{pc:#06X}  {count:>10}  {}", disasm::disassemble(op));
+    }
+}
 
-    // Clear to white and draw
-    canvas.set_draw_color(Color::WHITE);
+fn print_disassembly(args: &Args) -> ExitCode {
+    let mut data = Vec::new();
 
-    for (i, pixel) in screen_buf.iter().enumerate() {
-        if *pixel {
-            let x = (i % SCREEN_WIDTH) as u32;
-            let y = (i / SCREEN_WIDTH) as u32;
+    if let Err(err) = open_rom(args).and_then(|mut rom| rom.read_to_end(&mut data)) {
+        eprintln!("Unable to load emulator file! {err}");
+        return ExitCode::FAILURE;
+    }
 
-            let rect = Rect::new((x * SCALE) as i32, (y * SCALE) as i32, SCALE, SCALE);
-            canvas.fill_rect(rect).unwrap();
+    if let Some(listing_path) = &args.listing_path {
+        if let Err(err) = fs::write(listing_path, disasm::listing(&data)) {
+            eprintln!("Unable to write {listing_path}: {err}");
+            return ExitCode::FAILURE;
         }
+
+        return ExitCode::SUCCESS;
     }
 
-    canvas.present();
+    for (addr, mnemonic) in disasm::disassemble_rom(&data) {
+        println!("{addr:#06X}: {mnemonic}");
+    }
+
+    ExitCode::SUCCESS
+}
+
+// Fails when there are any problems, so scripts can check a batch of ROMs. Notes are only
+// printed.
+fn check_rom(args: &Args) -> ExitCode {
+    let mut data = Vec::new();
+
+    if let Err(err) = open_rom(args).and_then(|mut rom| rom.read_to_end(&mut data)) {
+        eprintln!("Unable to load emulator file! {err}");
+        return ExitCode::FAILURE;
+    }
+
+    let report = disasm::check(&data);
+
+    for (addr, problem) in &report.problems {
+        println!("{addr:#06X}: {problem}");
+    }
+
+    for (addr, note) in &report.notes {
+        println!("{addr:#06X}: note, {note}");
+    }
+
+    if report.problems.is_empty() {
+        println!("No problems found in {}", args.rom_path);
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
 }
 
-fn create_and_load_emulator(file: &str) -> io::Result<Emu> {
-    let data = fs::read(file)?;
+// Whitespace is skipped, so text wrapped over several lines works
+fn decode_base64(base64: &str) -> io::Result<Vec<u8>> {
+    let mut text = base64.to_string();
 
-    let mut emu = Emu::new();
+    if base64 == "-" {
+        text.clear();
+        io::stdin().read_to_string(&mut text)?;
+    }
+
+    text.retain(|c| !c.is_whitespace());
+
+    BASE64_STANDARD
+        .decode(text)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("invalid base64, {err}")))
+}
+
+fn create_and_load_emulator(args: &Args) -> Result<Emu, Box<dyn error::Error>> {
+    let mut rom = Vec::new();
+    open_rom(args)?.read_to_end(&mut rom)?;
 
-    emu.load(&data);
+    let config = match args.quirks {
+        Some(quirks) => EmuConfig::for_quirks(quirks),
+        None => detect_config(&rom),
+    };
+    let mut emu = Emu::new_with_config(with_quirk_flags(config, args));
+
+    emu.load(&rom)?;
 
     Ok(emu)
 }
 
-fn key_to_button(key: Keycode) -> Option<usize> {
-    match key {
-        Keycode::Num1 => Some(0x1),
-        Keycode::Num2 => Some(0x2),
-        Keycode::Num3 => Some(0x3),
-        Keycode::Num4 => Some(0xC),
-        Keycode::Q => Some(0x4),
-        Keycode::W | Keycode::Up => Some(0x5),
-        Keycode::E => Some(0x6),
-        Keycode::R => Some(0xD),
-        Keycode::A | Keycode::Left => Some(0x7),
-        Keycode::S | Keycode::Down => Some(0x8),
-        Keycode::D | Keycode::Right => Some(0x9),
-        Keycode::F => Some(0xE),
-        Keycode::Z => Some(0xA),
-        Keycode::X => Some(0x0),
-        Keycode::C => Some(0xB),
-        Keycode::V => Some(0xF),
-        _ => None,
+// Any --quirk flags on top of the profile. The profile alone picks the RAM size, so turning a
+// quirk off doesn't take away xochip's 64 KB.
+fn with_quirk_flags(mut config: EmuConfig, args: &Args) -> EmuConfig {
+    for &(name, enabled) in &args.quirk_flags {
+        config.quirks.set(name, enabled);
+    }
+
+    config
+}
+
+// For --quirks auto. Says which variant and profile were picked, as a game misbehaving might
+// need others.
+fn detect_config(rom: &[u8]) -> EmuConfig {
+    match romdb::lookup(rom) {
+        Some(entry) => {
+            println!(
+                "Recognized {}, running it as {} with the {} quirks",
+                entry.name, entry.variant, entry.profile
+            );
+
+            EmuConfig {
+                quirks: Quirks::preset(entry.profile)
+                    .expect("the ROM database only uses known profiles"),
+                ..EmuConfig::variant(entry.variant)
+                    .expect("the ROM database only uses known variants")
+            }
+        }
+        None => {
+            println!(
+                "Unrecognized game, using the {} quirks",
+                romdb::DEFAULT_PROFILE
+            );
+
+            EmuConfig::for_quirks(
+                Quirks::preset(romdb::DEFAULT_PROFILE).expect("the default profile is known"),
+            )
+        }
+    }
+}
+
+// A path of - reads the ROM from stdin, so it can be piped in. Demos are read from the binary,
+// URLs downloaded, and zips and base64 unpacked.
+fn open_rom(args: &Args) -> io::Result<Box<dyn Read>> {
+    let path = Path::new(&args.rom_path);
+
+    if let Some(demo) = args.demo {
+        Ok(Box::new(demo))
+    } else if let Some(base64) = &args.base64 {
+        Ok(Box::new(io::Cursor::new(decode_base64(base64)?)))
+    } else if let Some(url) = &args.url {
+        Ok(Box::new(io::Cursor::new(net::download(url)?)))
+    } else if args.rom_path == "-" {
+        Ok(Box::new(io::stdin()))
+    } else if archive::is_zip(path) {
+        let rom = archive::read_rom(path, args.entry.as_deref(), true)?;
+
+        Ok(Box::new(io::Cursor::new(rom)))
+    } else {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_base64() {
+        assert_eq!(decode_base64("YOEgAA==").unwrap(), [0x60, 0xE1, 0x20, 0x00]);
+
+        // Wrapped over several lines
+        assert_eq!(
+            decode_base64("YO\n  Eg\r\nAA==\n").unwrap(),
+            [0x60, 0xE1, 0x20, 0x00]
+        );
+
+        let err = decode_base64("not base64!").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
     }
 }
@@ -0,0 +1,96 @@
+use std::fmt::Write;
+
+use chip8_emu::emu::Emu;
+
+pub const BYTES_PER_LINE: usize = 16;
+
+// Hex dump of len bytes of RAM from start, 16 to a line. The next instruction (at the PC) and
+// the byte I points at are marked in the margin of their lines, and also shown in inverse/
+// underlined when `ansi` is set. Like hexdump, runs of identical lines collapse into a "*".
+pub fn hex_dump(emu: &Emu, start: usize, len: usize, ansi: bool) -> String {
+    let memory = emu.memory();
+    let pc = emu.program_counter() as usize;
+    let i = emu.i_register() as usize;
+
+    // Past the end of RAM there's nothing to show
+    let start = start.min(memory.len()) / BYTES_PER_LINE * BYTES_PER_LINE;
+    let end = (start + len).min(memory.len());
+
+    let mut dump = String::new();
+    let mut last_line: Option<&[u8]> = None;
+    let mut collapsed = false;
+
+    for (line_idx, line) in memory[start..end].chunks(BYTES_PER_LINE).enumerate() {
+        let addr = start + line_idx * BYTES_PER_LINE;
+        let line_range = addr..addr + line.len();
+
+        let has_pc = line_range.contains(&pc) || line_range.contains(&(pc + 1));
+        let has_i = line_range.contains(&i);
+
+        if !has_pc && !has_i && last_line == Some(line) {
+            if !collapsed {
+                dump.push_str("*\n");
+                collapsed = true;
+            }
+            continue;
+        }
+
+        last_line = Some(line);
+        collapsed = false;
+
+        write!(dump, "{addr:#06X}:").unwrap();
+
+        for (offset, byte) in line.iter().enumerate() {
+            let byte_addr = addr + offset;
+
+            if ansi && (byte_addr == pc || byte_addr == pc + 1) {
+                write!(dump, " \x1b[7m{byte:02X}\x1b[0m").unwrap();
+            } else if ansi && byte_addr == i {
+                write!(dump, " \x1b[4m{byte:02X}\x1b[0m").unwrap();
+            } else {
+                write!(dump, " {byte:02X}").unwrap();
+            }
+        }
+
+        if has_pc {
+            dump.push_str("  <- PC");
+        }
+        if has_i {
+            dump.push_str("  <- I");
+        }
+
+        dump.push('\n');
+    }
+
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use chip8_emu::constants::RAM_SIZE;
+
+    use super::*;
+
+    #[test]
+    fn dumps_lines_of_ram() {
+        let mut emu = Emu::new();
+        emu.load(&[0x12, 0x34]).unwrap();
+
+        assert_eq!(
+            hex_dump(&emu, 0x205, 16, false),
+            "0x0200: 12 34 00 00 00 00 00 00 00 00 00 00 00 00 00 00  <- PC\n"
+        );
+    }
+
+    #[test]
+    fn dumps_nothing_past_the_end_of_ram() {
+        let emu = Emu::new();
+
+        assert_eq!(hex_dump(&emu, RAM_SIZE, 16, false), "");
+        assert_eq!(hex_dump(&emu, RAM_SIZE + 0x10, 16 * 8, false), "");
+        assert_eq!(
+            hex_dump(&emu, RAM_SIZE - 1, 16 * 8, false).lines().count(),
+            1
+        );
+    }
+}
@@ -0,0 +1,35 @@
+// Games can be run straight from a URL. Downloading them needs the net feature, so builds
+// without it don't pull in an HTTP client.
+
+use std::io;
+
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+// The last part of the URL's path, to name captures and save states after
+pub fn file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+
+    match path.rsplit('/').next() {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => "download.ch8".to_string(),
+    }
+}
+
+#[cfg(feature = "net")]
+pub fn download(url: &str) -> io::Result<Vec<u8>> {
+    // Anything but a 2xx response is an error too
+    ureq::get(url)
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_vec())
+        .map_err(|err| io::Error::other(format!("unable to download {url}, {err}")))
+}
+
+#[cfg(not(feature = "net"))]
+pub fn download(url: &str) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("unable to download {url}, this build doesn't have the net feature"),
+    ))
+}
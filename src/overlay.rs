@@ -0,0 +1,234 @@
+use chip8_emu::{constants::SCREEN_WIDTH, emu::Emu};
+use sdl2::{
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas},
+    video::Window,
+};
+
+// 3x5 glyphs for just the characters the overlay needs, one row per byte with the leftmost
+// pixel in bit 2
+const GLYPHS: [(char, [u8; 5]); 21] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+];
+
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+
+// Characters across the widest line, plus a margin, which the text is scaled to fit
+const LINE_CHARS: u32 = 52;
+
+// The registers and timers, as drawn across the top of the window
+pub fn lines(emu: &Emu) -> Vec<String> {
+    let registers: Vec<_> = emu
+        .registers()
+        .iter()
+        .map(|reg| format!("{reg:02X}"))
+        .collect();
+
+    vec![
+        format!("V {}", registers.join(" ")),
+        format!(
+            "I {:04X}  PC {:04X}  SP {:X}  DT {:02X}  ST {:02X}",
+            emu.i_register(),
+            emu.program_counter(),
+            emu.stack_pointer(),
+            emu.delay_timer(),
+            emu.sound_timer(),
+        ),
+    ]
+}
+
+// Draws the lines over a translucent strip, so the game still shows through underneath
+pub fn draw(canvas: &mut Canvas<Window>, lines: &[String], color: Color) {
+    let (window_width, _) = canvas.output_size().unwrap();
+    let pixel_size = (window_width / ((GLYPH_WIDTH + 1) * LINE_CHARS)).max(1);
+    let line_height = (GLYPH_HEIGHT + 1) * pixel_size;
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 0xA0));
+    canvas
+        .fill_rect(Rect::new(
+            0,
+            0,
+            window_width,
+            line_height * lines.len() as u32 + pixel_size,
+        ))
+        .unwrap();
+    canvas.set_blend_mode(BlendMode::None);
+
+    canvas.set_draw_color(color);
+
+    for (line_idx, line) in lines.iter().enumerate() {
+        let top = pixel_size + line_idx as u32 * line_height;
+
+        for (char_idx, c) in line.chars().enumerate() {
+            let left = pixel_size + char_idx as u32 * (GLYPH_WIDTH + 1) * pixel_size;
+
+            // Anything without a glyph is drawn as a space
+            let Some((_, rows)) = GLYPHS.iter().find(|(glyph, _)| *glyph == c) else {
+                continue;
+            };
+
+            for (y, row) in rows.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if row & (0b100 >> x) != 0 {
+                        let rect = Rect::new(
+                            (left + x * pixel_size) as i32,
+                            (top + y as u32 * pixel_size) as i32,
+                            pixel_size,
+                            pixel_size,
+                        );
+                        canvas.fill_rect(rect).unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Rows shown for the sprite at I when the PC isn't at a DXYN to say how tall it is, the most a
+// lo-res sprite can have
+const DEFAULT_SPRITE_HEIGHT: usize = 15;
+
+const SPRITE_BOX_COLOR: Color = Color::RGB(0xFF, 0x40, 0x40);
+
+// The sprite I points at, as the DXYN at the PC would draw it
+pub struct Sprite {
+    // Where on the display it'd go, if the PC is at a DXYN
+    pub position: Option<(usize, usize)>,
+    // 8, or 16 for an SCHIP DXY0
+    pub width: usize,
+    // One row per entry, with the leftmost pixel in the top bit of `width`
+    pub rows: Vec<u16>,
+}
+
+pub fn sprite(emu: &Emu) -> Sprite {
+    let memory = emu.memory();
+    let byte = |addr: usize| memory[addr % memory.len()];
+
+    let pc = emu.program_counter() as usize;
+    let op = u16::from_be_bytes([byte(pc), byte(pc + 1)]);
+
+    let (position, width, height) = if op & 0xF000 == 0xD000 {
+        let (display_width, display_height) = emu.display_size();
+        let vx = emu.registers()[(op >> 8 & 0xF) as usize] as usize;
+        let vy = emu.registers()[(op >> 4 & 0xF) as usize] as usize;
+        let n = (op & 0xF) as usize;
+        let hires = display_width > SCREEN_WIDTH;
+
+        let (width, height) = if n == 0 && hires { (16, 16) } else { (8, n) };
+
+        (
+            Some((vx % display_width, vy % display_height)),
+            width,
+            height,
+        )
+    } else {
+        (None, 8, DEFAULT_SPRITE_HEIGHT)
+    };
+
+    let i = emu.i_register() as usize;
+
+    let rows = (0..height)
+        .map(|row| {
+            if width == 16 {
+                u16::from_be_bytes([byte(i + row * 2), byte(i + row * 2 + 1)])
+            } else {
+                byte(i + row) as u16
+            }
+        })
+        .collect();
+
+    Sprite {
+        position,
+        width,
+        rows,
+    }
+}
+
+// Boxes in where the sprite would be drawn on the display, which sits at `display_rect` and
+// is `width` pixels across, and shows its bits big in the bottom right corner of the window
+pub fn draw_sprite(
+    canvas: &mut Canvas<Window>,
+    sprite: &Sprite,
+    display_rect: Rect,
+    width: usize,
+    color: Color,
+) {
+    let scale = display_rect.width() as f32 / width as f32;
+
+    if let Some((x, y)) = sprite.position {
+        let rect = Rect::new(
+            display_rect.x() + (x as f32 * scale) as i32,
+            display_rect.y() + (y as f32 * scale) as i32,
+            (sprite.width as f32 * scale) as u32,
+            (sprite.rows.len().max(1) as f32 * scale) as u32,
+        );
+
+        // Off the edges it wraps or gets clipped, so only the part on the display is boxed
+        if let Some(rect) = rect.intersection(display_rect) {
+            canvas.set_draw_color(SPRITE_BOX_COLOR);
+            canvas.draw_rect(rect).unwrap();
+        }
+    }
+
+    let (window_width, window_height) = canvas.output_size().unwrap();
+    let cell = (window_width / 128).max(2);
+    let (panel_width, panel_height) = (
+        (sprite.width as u32 + 2) * cell,
+        (sprite.rows.len() as u32 + 2) * cell,
+    );
+    let (left, top) = (
+        window_width.saturating_sub(panel_width) as i32,
+        window_height.saturating_sub(panel_height) as i32,
+    );
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 0xA0));
+    canvas
+        .fill_rect(Rect::new(left, top, panel_width, panel_height))
+        .unwrap();
+    canvas.set_blend_mode(BlendMode::None);
+
+    canvas.set_draw_color(SPRITE_BOX_COLOR);
+    canvas
+        .draw_rect(Rect::new(left, top, panel_width, panel_height))
+        .unwrap();
+
+    canvas.set_draw_color(color);
+
+    for (y, row) in sprite.rows.iter().enumerate() {
+        for x in 0..sprite.width {
+            if row & (1 << (sprite.width - 1 - x)) != 0 {
+                let rect = Rect::new(
+                    left + ((x as u32 + 1) * cell) as i32,
+                    top + ((y as u32 + 1) * cell) as i32,
+                    cell,
+                    cell,
+                );
+                canvas.fill_rect(rect).unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use sdl2::pixels::Color;
+
+// Colors used to draw lit (fg) and unlit (bg) pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub fg: Color,
+    pub bg: Color,
+}
+
+// Named palettes for --theme
+const THEMES: [(&str, Palette); 5] = [
+    (
+        "classic",
+        Palette {
+            fg: Color::WHITE,
+            bg: Color::BLACK,
+        },
+    ),
+    (
+        "amber",
+        Palette {
+            fg: Color::RGB(0xFF, 0xB0, 0x00),
+            bg: Color::RGB(0x1A, 0x10, 0x00),
+        },
+    ),
+    (
+        "green-phosphor",
+        Palette {
+            fg: Color::RGB(0x33, 0xFF, 0x66),
+            bg: Color::RGB(0x0A, 0x1A, 0x0F),
+        },
+    ),
+    (
+        "gameboy",
+        Palette {
+            fg: Color::RGB(0x0F, 0x38, 0x0F),
+            bg: Color::RGB(0x9B, 0xBC, 0x0F),
+        },
+    ),
+    (
+        "inverted",
+        Palette {
+            fg: Color::BLACK,
+            bg: Color::WHITE,
+        },
+    ),
+];
+
+impl Palette {
+    pub fn theme(name: &str) -> Option<Self> {
+        THEMES
+            .iter()
+            .find(|(theme_name, _)| *theme_name == name)
+            .map(|&(_, palette)| palette)
+    }
+
+    pub fn theme_names() -> impl Iterator<Item = &'static str> {
+        THEMES.iter().map(|&(name, _)| name)
+    }
+
+    pub fn inverted(&self) -> Self {
+        Self {
+            fg: self.bg,
+            bg: self.fg,
+        }
+    }
+
+    // Partway between the colors, from bg at 0 to fg at 1
+    pub fn blend(&self, amount: f32) -> Color {
+        let mix = |bg: u8, fg: u8| (bg as f32 + (fg as f32 - bg as f32) * amount).round() as u8;
+
+        Color::RGB(
+            mix(self.bg.r, self.fg.r),
+            mix(self.bg.g, self.fg.g),
+            mix(self.bg.b, self.fg.b),
+        )
+    }
+}
+
+impl Default for Palette {
+    // Classic white on black
+    fn default() -> Self {
+        THEMES[0].1
+    }
+}
+
+// Parses hex colors like #33FF66, or the shorthand #3F6. The # is optional.
+pub fn parse_hex_color(hex: &str) -> Option<Color> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let channels: Vec<u8> = match digits.len() {
+        // Each digit is doubled up, so F becomes FF
+        3 => digits
+            .chars()
+            .map(|c| c.to_digit(16).unwrap() as u8 * 0x11)
+            .collect(),
+        6 => (0..6)
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&digits[idx..idx + 2], 16).unwrap())
+            .collect(),
+        _ => return None,
+    };
+
+    Some(Color::RGB(channels[0], channels[1], channels[2]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_up_themes() {
+        let themes = [
+            ("classic", "#FFFFFF", "#000000"),
+            ("amber", "#FFB000", "#1A1000"),
+            ("green-phosphor", "#33FF66", "#0A1A0F"),
+            ("gameboy", "#0F380F", "#9BBC0F"),
+            ("inverted", "#000000", "#FFFFFF"),
+        ];
+
+        for (name, fg, bg) in themes {
+            assert_eq!(
+                Palette::theme(name),
+                Some(Palette {
+                    fg: parse_hex_color(fg).unwrap(),
+                    bg: parse_hex_color(bg).unwrap(),
+                }),
+                "{name}"
+            );
+        }
+
+        assert!(Palette::theme_names().eq(themes.map(|(name, _, _)| name)));
+        assert_eq!(Palette::theme("sepia"), None);
+        assert_eq!(Palette::default(), Palette::theme("classic").unwrap());
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(
+            parse_hex_color("#33FF66"),
+            Some(Color::RGB(0x33, 0xFF, 0x66))
+        );
+        assert_eq!(
+            parse_hex_color("ffb000"),
+            Some(Color::RGB(0xFF, 0xB0, 0x00))
+        );
+        assert_eq!(parse_hex_color("#3F6"), Some(Color::RGB(0x33, 0xFF, 0x66)));
+        assert_eq!(parse_hex_color("fff"), Some(Color::WHITE));
+    }
+
+    #[test]
+    fn rejects_bad_hex_colors() {
+        for hex in [
+            "", "#", "#33FF6", "#33FF667", "#GGGGGG", "##33FF66", "#+3FF66", "red",
+        ] {
+            assert_eq!(parse_hex_color(hex), None, "{hex}");
+        }
+    }
+}
@@ -0,0 +1,238 @@
+// Behaviors that differ between CHIP-8 interpreters, and which ROMs tend to rely on. The
+// defaults match Cowgod's specification, which this emulator has followed from the start, apart
+// from FX0A waiting for a key release like the original interpreter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    // 8XY6/8XYE copy VY into VX before shifting, instead of shifting VX in place
+    pub shift_uses_vy: bool,
+
+    // FX55/FX65 leave I pointing just past the last register stored/loaded
+    pub increment_i_on_store_load: bool,
+
+    // BNNN is treated as BXNN, jumping to VX + NN instead of V0 + NNN
+    pub jump_with_vx: bool,
+
+    // 8XY1/8XY2/8XY3 reset VF to 0
+    pub vf_reset_on_logic: bool,
+
+    // DXYN wraps sprites running off the left/right edges of the screen around to the other
+    // side, rather than clipping them. Some games want that on just one axis.
+    pub wrap_x: bool,
+
+    // The same for the top/bottom edges
+    pub wrap_y: bool,
+
+    // FX0A completes as soon as a key is down, rather than once it's released
+    pub key_wait_on_press: bool,
+
+    // The PC wraps back to the start of RAM when it runs off the end, instead of stopping
+    // with an error
+    pub wrap_pc: bool,
+
+    // 00FB/00FC scroll by 2 pixels instead of 4 in lo-res mode
+    pub half_scroll_in_lores: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            shift_uses_vy: false,
+            increment_i_on_store_load: false,
+            jump_with_vx: false,
+            vf_reset_on_logic: false,
+            wrap_x: true,
+            wrap_y: true,
+            key_wait_on_press: false,
+            wrap_pc: false,
+            half_scroll_in_lores: false,
+        }
+    }
+}
+
+impl Quirks {
+    pub const PRESETS: [&str; 4] = ["cowgod", "chip8", "schip", "xochip"];
+
+    // The flags by name, for `set`
+    pub const NAMES: [&str; 9] = [
+        "shift_uses_vy",
+        "increment_i_on_store_load",
+        "jump_with_vx",
+        "vf_reset_on_logic",
+        "wrap_x",
+        "wrap_y",
+        "key_wait_on_press",
+        "wrap_pc",
+        "half_scroll_in_lores",
+    ];
+
+    // Turns a single flag on or off by name, as --quirk takes it, e.g. on top of a profile.
+    // Returns false if there's no flag by that name.
+    pub fn set(&mut self, name: &str, enabled: bool) -> bool {
+        let flag = match name {
+            "shift_uses_vy" => &mut self.shift_uses_vy,
+            "increment_i_on_store_load" => &mut self.increment_i_on_store_load,
+            "jump_with_vx" => &mut self.jump_with_vx,
+            "vf_reset_on_logic" => &mut self.vf_reset_on_logic,
+            "wrap_x" => &mut self.wrap_x,
+            "wrap_y" => &mut self.wrap_y,
+            "key_wait_on_press" => &mut self.key_wait_on_press,
+            "wrap_pc" => &mut self.wrap_pc,
+            "half_scroll_in_lores" => &mut self.half_scroll_in_lores,
+            _ => return false,
+        };
+
+        *flag = enabled;
+
+        true
+    }
+
+    // Picks a profile by name, as --quirks takes it: cowgod, chip8, schip or xochip
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "cowgod" => Some(Self::cowgod()),
+            "chip8" => Some(Self::chip8()),
+            "schip" => Some(Self::schip()),
+            "xochip" => Some(Self::xochip()),
+            _ => None,
+        }
+    }
+
+    // The name of the profile these are, if they're one of the presets
+    pub fn preset_name(&self) -> Option<&'static str> {
+        Self::PRESETS
+            .into_iter()
+            .find(|name| Self::preset(name) == Some(*self))
+    }
+
+    // Cowgod's specification, the default
+    pub fn cowgod() -> Self {
+        Self::default()
+    }
+
+    // The original COSMAC VIP interpreter
+    pub fn chip8() -> Self {
+        Self {
+            shift_uses_vy: true,
+            increment_i_on_store_load: true,
+            jump_with_vx: false,
+            vf_reset_on_logic: true,
+            wrap_x: false,
+            wrap_y: false,
+            key_wait_on_press: false,
+            wrap_pc: false,
+            half_scroll_in_lores: false,
+        }
+    }
+
+    // SUPER-CHIP 1.1 on the HP-48
+    pub fn schip() -> Self {
+        Self {
+            shift_uses_vy: false,
+            increment_i_on_store_load: false,
+            jump_with_vx: true,
+            vf_reset_on_logic: false,
+            wrap_x: false,
+            wrap_y: false,
+            key_wait_on_press: false,
+            wrap_pc: false,
+            half_scroll_in_lores: true,
+        }
+    }
+
+    // XO-CHIP as Octo runs it, which went back to the VIP's shifts and loads/stores but wraps
+    // sprites around the screen
+    pub fn xochip() -> Self {
+        Self {
+            shift_uses_vy: true,
+            increment_i_on_store_load: true,
+            jump_with_vx: false,
+            vf_reset_on_logic: false,
+            wrap_x: true,
+            wrap_y: true,
+            key_wait_on_press: false,
+            wrap_pc: false,
+            half_scroll_in_lores: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_up_presets() {
+        for name in Quirks::PRESETS {
+            let quirks = Quirks::preset(name).unwrap();
+
+            assert_eq!(quirks.preset_name(), Some(name));
+        }
+
+        assert_eq!(Quirks::preset("cowgod"), Some(Quirks::default()));
+        assert_eq!(Quirks::preset("vip"), None);
+
+        let custom = Quirks {
+            wrap_pc: true,
+            ..Quirks::default()
+        };
+        assert_eq!(custom.preset_name(), None);
+    }
+
+    #[test]
+    fn sets_each_presets_flags() {
+        assert_eq!(
+            Quirks::chip8(),
+            Quirks {
+                shift_uses_vy: true,
+                increment_i_on_store_load: true,
+                vf_reset_on_logic: true,
+                wrap_x: false,
+                wrap_y: false,
+                ..Quirks::default()
+            }
+        );
+        assert_eq!(
+            Quirks::schip(),
+            Quirks {
+                jump_with_vx: true,
+                wrap_x: false,
+                wrap_y: false,
+                half_scroll_in_lores: true,
+                ..Quirks::default()
+            }
+        );
+        assert_eq!(
+            Quirks::xochip(),
+            Quirks {
+                shift_uses_vy: true,
+                increment_i_on_store_load: true,
+                ..Quirks::default()
+            }
+        );
+    }
+
+    #[test]
+    fn sets_flags_by_name() {
+        let mut quirks = Quirks::default();
+
+        for name in Quirks::NAMES {
+            assert!(quirks.set(name, true));
+        }
+
+        assert!(!quirks.set("wrap_z", true));
+        assert_eq!(
+            quirks,
+            Quirks {
+                shift_uses_vy: true,
+                increment_i_on_store_load: true,
+                jump_with_vx: true,
+                vf_reset_on_logic: true,
+                wrap_x: true,
+                wrap_y: true,
+                key_wait_on_press: true,
+                wrap_pc: true,
+                half_scroll_in_lores: true,
+            }
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use std::{borrow::Cow, fs::File, path::Path};
+
+use chip8_emu::{
+    constants::{HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH},
+    emu::Emu,
+};
+use gif::{Encoder, EncodingError, Frame, Repeat};
+
+use crate::palette::Palette;
+
+// Frames are kept at the hi-res resolution (lo-res pixels are doubled up) so ROMs can switch
+// modes mid-recording, then blown up by this much when encoded
+const GIF_SCALE: usize = 4;
+
+// A minute at 60 FPS, past which frames are dropped
+const MAX_FRAMES: usize = 3600;
+
+// Collects one frame per emulated frame while recording, and encodes them all on finish
+pub struct GifRecorder {
+    palette: Palette,
+    frames: Vec<Vec<bool>>,
+}
+
+impl GifRecorder {
+    pub fn start(palette: Palette) -> Self {
+        Self {
+            palette,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn push_frame(&mut self, emu: &Emu) {
+        if self.is_full() {
+            return;
+        }
+
+        let (width, _) = emu.display_size();
+        let screen_buf = emu.get_display();
+        let factor = HIRES_SCREEN_WIDTH / width;
+
+        let frame = (0..HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT)
+            .map(|i| {
+                let x = i % HIRES_SCREEN_WIDTH / factor;
+                let y = i / HIRES_SCREEN_WIDTH / factor;
+
+                screen_buf[y * width + x]
+            })
+            .collect();
+
+        self.frames.push(frame);
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.frames.len() >= MAX_FRAMES
+    }
+
+    pub fn finish(self, path: &Path) -> Result<(), EncodingError> {
+        let width = HIRES_SCREEN_WIDTH * GIF_SCALE;
+        let height = HIRES_SCREEN_HEIGHT * GIF_SCALE;
+
+        // Index 0 is the background and 1 the foreground
+        let Palette { fg, bg } = self.palette;
+        let color_map = [bg.r, bg.g, bg.b, fg.r, fg.g, fg.b];
+
+        let file = File::create(path)?;
+        let mut encoder = Encoder::new(file, width as u16, height as u16, &color_map)?;
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        for (n, frame) in self.frames.iter().enumerate() {
+            let pixels: Vec<u8> = (0..width * height)
+                .map(|i| {
+                    let x = i % width / GIF_SCALE;
+                    let y = i / width / GIF_SCALE;
+
+                    frame[y * HIRES_SCREEN_WIDTH + x] as u8
+                })
+                .collect();
+
+            // GIF delays are in hundredths of a second, so 2, 2, 1 averages out to 60 FPS
+            let delay = if n % 3 == 2 { 1 } else { 2 };
+
+            encoder.write_frame(&Frame {
+                width: width as u16,
+                height: height as u16,
+                delay,
+                buffer: Cow::Owned(pixels),
+                ..Frame::default()
+            })?;
+        }
+
+        Ok(())
+    }
+}
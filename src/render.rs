@@ -0,0 +1,53 @@
+use alloc::vec::Vec;
+
+// Something that can show the display. `screen` is laid out row by row, `width` pixels wide,
+// like `Emu::get_display`.
+pub trait Renderer {
+    fn draw(&mut self, screen: &[bool], width: usize, height: usize);
+}
+
+// Keeps a copy of the last frame drawn, for checking what a frontend would have shown
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufferRenderer {
+    pub screen: Vec<bool>,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Renderer for BufferRenderer {
+    fn draw(&mut self, screen: &[bool], width: usize, height: usize) {
+        self.screen = screen.to_vec();
+        self.width = width;
+        self.height = height;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::run;
+
+    #[test]
+    fn records_the_last_frame() {
+        // The top row of the font's 1, ..#., at (1, 1)
+        let emu = run("LD V0, 1\nLD F, V0\nDRW V0, V0, 1", 3);
+        let mut renderer = BufferRenderer::default();
+        emu.render(&mut renderer);
+
+        assert_eq!((renderer.width, renderer.height), (64, 32));
+        assert_eq!(renderer.screen, emu.get_display());
+
+        let lit: Vec<_> = (0..renderer.screen.len())
+            .filter(|&idx| renderer.screen[idx])
+            .collect();
+        assert_eq!(lit, [64 + 3]);
+
+        // Hi-res replaces the whole frame
+        let emu = run("HIGH", 1);
+        emu.render(&mut renderer);
+
+        assert_eq!((renderer.width, renderer.height), (128, 64));
+        assert_eq!(renderer.screen.len(), 128 * 64);
+        assert!(renderer.screen.iter().all(|&pixel| !pixel));
+    }
+}
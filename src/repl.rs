@@ -0,0 +1,311 @@
+use std::{
+    io::{self, BufRead, IsTerminal, Write},
+    process::ExitCode,
+};
+
+use chip8_emu::{
+    debugger::{Debugger, parse_hex},
+    disasm::disassemble,
+    emu::{Emu, EmuError},
+};
+use sdl2::{EventPump, event::Event, keyboard::Keycode};
+
+use crate::{keymap::Keymap, memview, sdl_renderer::SdlRenderer};
+
+// Lines of memory the mem command shows
+const MEM_LINES: usize = 8;
+
+const HELP: &str = "\
+Commands:
+  s, step [n]      execute n instructions (default 1)
+  c, continue      run until the next breakpoint (Escape in the window pauses)
+  sk, skip         move past the instruction at the PC without executing it
+  b, break ADDR    add a breakpoint at ADDR (hex)
+  wa, watch ADDR   stop after any write to ADDR
+  u, unwatch ADDR  remove the watchpoint on ADDR
+  w, when COND     break once COND becomes true, e.g. when V3 == 0A (values in hex)
+  d, delete ADDR   remove the breakpoint at ADDR, or #N for condition N
+  dp, disp EXPR    show EXPR after every step, e.g. disp V0 + V1, mem[I] or [300]
+  ud, undisp N     stop showing expression N
+  l, list          list breakpoints, watchpoints, conditions and expressions
+  m, mem [ADDR]    show the memory around ADDR (hex, default the PC)
+  st, state        print the registers, stack and display, e.g. for a bug report
+  h, help          show this message
+  q, quit          exit";
+
+// Stdin-driven debugger. The window keeps showing the display, and takes input while the
+// program is running. With `break_on_unknown`, an unknown opcode stops at the prompt rather
+// than ending the session.
+pub fn run(
+    emu: &mut Emu,
+    renderer: &mut SdlRenderer,
+    event_pump: &mut EventPump,
+    keymap: &Keymap,
+    break_on_unknown: bool,
+) -> ExitCode {
+    let mut debugger = Debugger::new();
+    let mut lines = io::stdin().lock().lines();
+
+    println!("{HELP}");
+    print_location(emu);
+
+    loop {
+        emu.render(renderer);
+
+        print!("> ");
+        io::stdout().flush().unwrap();
+
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            // EOF or unreadable stdin
+            _ => return ExitCode::SUCCESS,
+        };
+
+        let mut words = line.split_whitespace();
+
+        let result = match (words.next(), words.next()) {
+            (None, _) => continue,
+            (Some("s" | "step"), count) => {
+                let count = match count.map(str::parse) {
+                    None => 1,
+                    Some(Ok(count)) => count,
+                    Some(Err(_)) => {
+                        println!("Invalid step count");
+                        continue;
+                    }
+                };
+
+                (0..count).try_for_each(|_| debugger.step(emu))
+            }
+            (Some("c" | "continue"), _) => {
+                run_until_break(&mut debugger, emu, renderer, event_pump, keymap)
+            }
+            (Some("sk" | "skip"), _) => {
+                emu.set_program_counter(emu.program_counter().wrapping_add(2));
+                print_location(emu);
+                continue;
+            }
+            (Some("b" | "break"), Some(addr)) => {
+                match parse_hex(addr) {
+                    Some(addr) => debugger.add_breakpoint(addr),
+                    None => println!("Invalid address {addr}"),
+                }
+                continue;
+            }
+            (Some("wa" | "watch"), Some(addr)) => {
+                match parse_hex(addr) {
+                    Some(addr) => debugger.add_watchpoint(addr),
+                    None => println!("Invalid address {addr}"),
+                }
+                continue;
+            }
+            (Some("u" | "unwatch"), Some(addr)) => {
+                match parse_hex(addr) {
+                    Some(addr) if debugger.remove_watchpoint(addr) => (),
+                    _ => println!("No watchpoint on {addr}"),
+                }
+                continue;
+            }
+            (Some("w" | "when"), Some(_)) => {
+                // The condition is everything after the command, spaces and all
+                let condition = line.trim().split_once(char::is_whitespace).unwrap().1;
+
+                match condition.parse() {
+                    Ok(condition) => debugger.add_condition(condition),
+                    Err(err) => println!("Invalid condition: {err}"),
+                }
+                continue;
+            }
+            (Some("dp" | "disp" | "display"), Some(_)) => {
+                let expr = line.trim().split_once(char::is_whitespace).unwrap().1;
+
+                match expr.parse() {
+                    Ok(expr) => {
+                        debugger.add_display(expr);
+                        print_displays(&debugger, emu);
+                    }
+                    Err(err) => println!("Invalid expression: {err}"),
+                }
+                continue;
+            }
+            (Some("ud" | "undisp" | "undisplay"), Some(n)) => {
+                match n.parse::<usize>() {
+                    Ok(n) if n > 0 && debugger.remove_display(n - 1) => (),
+                    _ => println!("No expression {n}"),
+                }
+                continue;
+            }
+            (Some("d" | "delete"), Some(condition)) if condition.starts_with('#') => {
+                match condition[1..].parse::<usize>() {
+                    Ok(n) if n > 0 && debugger.remove_condition(n - 1) => (),
+                    _ => println!("No condition {condition}"),
+                }
+                continue;
+            }
+            (Some("d" | "delete"), Some(addr)) => {
+                match parse_hex(addr) {
+                    Some(addr) if debugger.remove_breakpoint(addr) => (),
+                    _ => println!("No breakpoint at {addr}"),
+                }
+                continue;
+            }
+            (Some("l" | "list"), _) => {
+                let mut breakpoints: Vec<_> = debugger.breakpoints().collect();
+                breakpoints.sort_unstable();
+
+                for addr in breakpoints {
+                    println!("{addr:#06X}");
+                }
+
+                let mut watchpoints: Vec<_> = debugger.watchpoints().collect();
+                watchpoints.sort_unstable();
+
+                for addr in watchpoints {
+                    println!("{addr:#06X} (watch)");
+                }
+
+                for (n, condition) in debugger.conditions().enumerate() {
+                    println!("#{} {condition}", n + 1);
+                }
+
+                for (n, expr) in debugger.displays().enumerate() {
+                    println!("{}: {expr}", n + 1);
+                }
+                continue;
+            }
+            (Some("m" | "mem"), addr) => {
+                let addr = match addr.map(parse_hex) {
+                    None => Some(emu.program_counter()),
+                    Some(addr) => addr.filter(|&addr| (addr as usize) < emu.memory().len()),
+                };
+
+                match addr {
+                    Some(addr) => {
+                        // Start a line before, for some context
+                        let start = (addr as usize).saturating_sub(memview::BYTES_PER_LINE);
+                        let ansi = io::stdout().is_terminal();
+                        print!(
+                            "{}",
+                            memview::hex_dump(
+                                emu,
+                                start,
+                                MEM_LINES * memview::BYTES_PER_LINE,
+                                ansi
+                            )
+                        );
+                    }
+                    None => println!("Invalid address"),
+                }
+                continue;
+            }
+            (Some("st" | "state"), _) => {
+                print!("{}", emu.debug_dump());
+                continue;
+            }
+            (Some("h" | "help"), _) => {
+                println!("{HELP}");
+                continue;
+            }
+            (Some("q" | "quit"), _) => return ExitCode::SUCCESS,
+            (Some(command), _) => {
+                println!("Unknown command {command}, try help");
+                continue;
+            }
+        };
+
+        match result {
+            Err(EmuError::UnknownOpcode(op)) if break_on_unknown => stop_at_unknown(emu, op),
+            Err(err) => {
+                eprintln!("Emulation stopped: {err}");
+                return ExitCode::FAILURE;
+            }
+            Ok(()) => (),
+        }
+
+        crate::warn_code_writes(emu);
+
+        if let Some(write) = debugger.watch_hit() {
+            println!(
+                "{:#06X} changed from {:#04X} to {:#04X}",
+                write.addr, write.old, write.new
+            );
+        }
+
+        print_location(emu);
+        print_displays(&debugger, emu);
+    }
+}
+
+// Like `Debugger::continue_run`, but a frame at a time so the window stays live
+fn run_until_break(
+    debugger: &mut Debugger,
+    emu: &mut Emu,
+    renderer: &mut SdlRenderer,
+    event_pump: &mut EventPump,
+    keymap: &Keymap,
+) -> Result<(), chip8_emu::emu::EmuError> {
+    loop {
+        for _ in 0..emu.ticks_per_frame() {
+            debugger.step(emu)?;
+
+            if debugger.at_breakpoint(emu) {
+                return Ok(());
+            }
+        }
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => return Ok(()),
+                Event::KeyDown {
+                    keycode: Some(key), ..
+                } => {
+                    if let Some(k) = keymap.button(key) {
+                        emu.keypress(k, true);
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(key), ..
+                } => {
+                    if let Some(k) = keymap.button(key) {
+                        emu.keypress(k, false);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        emu.render(renderer);
+    }
+}
+
+// Goes back to an unknown opcode that was just fetched, so it can be looked at, and then
+// skipped or tried again
+pub fn stop_at_unknown(emu: &mut Emu, op: u16) {
+    let addr = emu.program_counter().wrapping_sub(2);
+    emu.set_program_counter(addr);
+
+    println!(
+        "Unknown opcode at {addr:#06X}: {:02X} {:02X}  {}",
+        op >> 8,
+        op & 0xFF,
+        disassemble(op)
+    );
+    println!("skip moves past it, or quit to stop");
+}
+
+fn print_location(emu: &Emu) {
+    println!("PC: {:#06X}", emu.program_counter());
+}
+
+fn print_displays(debugger: &Debugger, emu: &Emu) {
+    for (n, expr) in debugger.displays().enumerate() {
+        match expr.eval(emu) {
+            Ok(value) => println!("{}: {expr} = {value:#06X}", n + 1),
+            Err(err) => println!("{}: {expr} = <{err}>", n + 1),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use chip8_emu::{constants::NUM_KEYS, emu::Emu};
+
+// A keypad change, made just before the given frame is run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    pub frame: u64,
+    pub key: usize,
+    pub pressed: bool,
+}
+
+// Logs keypad changes as they happen, one `frame key pressed` line each, after the CXNN seed
+// and instructions per frame the run started with, which it needs to play out the same again:
+//
+//   seed 9271055913
+//   speed 10
+//   120 5 1
+//   134 5 0
+pub struct InputRecorder {
+    out: BufWriter<File>,
+}
+
+impl InputRecorder {
+    pub fn create(path: &Path, seed: u64, speed: usize) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+
+        writeln!(out, "seed {seed}")?;
+        writeln!(out, "speed {speed}")?;
+
+        Ok(Self { out })
+    }
+
+    pub fn record(&mut self, event: InputEvent) -> io::Result<()> {
+        let InputEvent {
+            frame,
+            key,
+            pressed,
+        } = event;
+
+        writeln!(self.out, "{frame} {key:X} {}", pressed as u8)
+    }
+}
+
+// Feeds a recorded log back into the emulator at the frames it was recorded on
+pub struct InputPlayer {
+    events: VecDeque<InputEvent>,
+    // Missing from logs recorded before they were written
+    seed: Option<u64>,
+    speed: Option<usize>,
+}
+
+impl InputPlayer {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let log = fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+
+        Self::parse(&log).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    pub fn parse(log: &str) -> Result<Self, String> {
+        let mut events = VecDeque::new();
+        let mut seed = None;
+        let mut speed = None;
+
+        for (line_idx, line) in log.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let err = || format!("line {}: expected frame key pressed", line_idx + 1);
+
+            if let Some(value) = line.strip_prefix("seed ") {
+                seed = Some(value.trim().parse().map_err(|_| err())?);
+                continue;
+            }
+
+            if let Some(value) = line.strip_prefix("speed ") {
+                speed = Some(value.trim().parse().map_err(|_| err())?);
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let mut field = || fields.next().ok_or_else(err);
+
+            let frame = field()?.parse().map_err(|_| err())?;
+            let key = usize::from_str_radix(field()?, 16)
+                .ok()
+                .filter(|&key| key < NUM_KEYS)
+                .ok_or_else(err)?;
+            let pressed = match field()? {
+                "1" => true,
+                "0" => false,
+                _ => return Err(err()),
+            };
+
+            events.push_back(InputEvent {
+                frame,
+                key,
+                pressed,
+            });
+        }
+
+        Ok(Self {
+            events,
+            seed,
+            speed,
+        })
+    }
+
+    // The seed the run was recorded with, which CXNN has to be seeded with again
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    // Instructions per frame the run was recorded at
+    pub fn speed(&self) -> Option<usize> {
+        self.speed
+    }
+
+    // Presses/releases everything recorded up to and including this frame
+    pub fn apply(&mut self, frame: u64, emu: &mut Emu) {
+        while let Some(event) = self.events.front().filter(|event| event.frame <= frame) {
+            emu.keypress(event.key, event.pressed);
+            self.events.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use chip8_emu::asm::assemble;
+
+    use super::*;
+
+    // Adds up random numbers while key 5 is held
+    const PROGRAM: &str = "
+        LD V3, 5
+        loop: RND V0, 0xFF
+        SKNP V3
+        ADD V1, V0
+        JP loop
+    ";
+
+    const FRAMES: u64 = 60;
+
+    fn emu_running() -> Emu {
+        let mut emu = Emu::new();
+        emu.load(&assemble(PROGRAM).unwrap()).unwrap();
+
+        emu
+    }
+
+    #[test]
+    fn replays_recorded_run() {
+        let path = env::temp_dir().join(format!("chip8_emu-replay-{}.log", process::id()));
+
+        let mut emu = emu_running();
+        emu.set_seed(1234);
+        emu.set_ticks_per_frame(7);
+
+        let mut recorder = InputRecorder::create(&path, 1234, 7).unwrap();
+
+        for frame in 0..FRAMES {
+            if frame % 20 == 5 || frame % 20 == 12 {
+                let event = InputEvent {
+                    frame,
+                    key: 5,
+                    pressed: frame % 20 == 5,
+                };
+
+                emu.keypress(event.key, event.pressed);
+                recorder.record(event).unwrap();
+            }
+
+            emu.run_frame().unwrap();
+        }
+
+        drop(recorder);
+
+        let mut player = InputPlayer::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(player.seed(), Some(1234));
+        assert_eq!(player.speed(), Some(7));
+
+        let mut replayed = emu_running();
+        replayed.set_seed(player.seed().unwrap());
+        replayed.set_ticks_per_frame(player.speed().unwrap());
+
+        for frame in 0..FRAMES {
+            player.apply(frame, &mut replayed);
+            replayed.run_frame().unwrap();
+        }
+
+        assert_ne!(emu.registers()[1], 0);
+        assert_eq!(replayed.save_state(), emu.save_state());
+    }
+
+    #[test]
+    fn parses_logs_without_a_seed() {
+        let player = InputPlayer::parse("120 5 1\n134 5 0\n").unwrap();
+
+        assert_eq!(player.seed(), None);
+        assert_eq!(player.events.len(), 2);
+
+        assert!(InputPlayer::parse("seed x").is_err());
+        assert!(InputPlayer::parse("120 G 1").is_err());
+    }
+}
@@ -0,0 +1,43 @@
+use alloc::{collections::VecDeque, vec::Vec};
+
+// Ring buffer of save states, newest at the back. Once full, the oldest snapshot is dropped
+// to make room. The default has a depth of 0, keeping nothing.
+#[derive(Default)]
+pub struct RewindBuffer {
+    snapshots: VecDeque<Vec<u8>>,
+    depth: usize,
+}
+
+impl RewindBuffer {
+    pub fn with_depth(depth: usize) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(depth),
+            depth,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn push(&mut self, snapshot: Vec<u8>) {
+        if self.depth == 0 {
+            return;
+        }
+
+        if self.snapshots.len() == self.depth {
+            self.snapshots.pop_front();
+        }
+
+        self.snapshots.push_back(snapshot);
+    }
+
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        self.snapshots.pop_back()
+    }
+
+    // Drops every snapshot, keeping the depth
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+}
@@ -0,0 +1,75 @@
+use sha1::{Digest, Sha1};
+
+// Known ROMs, the variant each was written for and the quirks profile it needs, for --quirks
+// auto
+const DATABASE: &str = include_str!("../roms/database.txt");
+
+// For ROMs that aren't in the database
+pub const DEFAULT_PROFILE: &str = "cowgod";
+
+pub struct Entry {
+    // Picks the machine, e.g. XO-CHIP's 64 KB, with `EmuConfig::variant`
+    pub variant: &'static str,
+    pub profile: &'static str,
+    pub name: &'static str,
+}
+
+pub fn lookup(rom: &[u8]) -> Option<Entry> {
+    let hash: String = Sha1::digest(rom)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    DATABASE
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .find_map(|line| {
+            let (sha1, rest) = line.split_once(char::is_whitespace)?;
+            let (variant, rest) = rest.trim_start().split_once(char::is_whitespace)?;
+            let (profile, name) = rest.trim_start().split_once(char::is_whitespace)?;
+
+            sha1.eq_ignore_ascii_case(&hash).then(|| Entry {
+                variant,
+                profile,
+                name: name.trim(),
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use chip8_emu::{EmuConfig, Quirks};
+
+    use super::*;
+
+    #[test]
+    fn looks_up_roms() {
+        let entry = lookup(include_bytes!("../roms/bounce.ch8")).unwrap();
+
+        assert_eq!(entry.name, "Bounce (demo)");
+        assert_eq!((entry.variant, entry.profile), ("chip8", "cowgod"));
+
+        assert!(lookup(b"not a game").is_none());
+    }
+
+    #[test]
+    fn only_uses_known_variants_and_profiles() {
+        for line in DATABASE
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        {
+            let mut columns = line.split_whitespace();
+            let (_, variant, profile) = (columns.next(), columns.next(), columns.next());
+
+            assert!(
+                variant.and_then(EmuConfig::variant).is_some(),
+                "unknown variant in {line}"
+            );
+            assert!(
+                profile.and_then(Quirks::preset).is_some(),
+                "unknown profile in {line}"
+            );
+        }
+    }
+}
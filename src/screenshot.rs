@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use chip8_emu::{constants::SCALE, emu::Emu};
+use image::{ImageResult, Rgba, RgbaImage};
+use sdl2::pixels::Color;
+
+use crate::palette::Palette;
+
+// Writes the display to a PNG, with each pixel blown up to SCALE x SCALE
+pub fn save(emu: &Emu, palette: Palette, path: &Path) -> ImageResult<()> {
+    let (width, height) = emu.display_size();
+    let screen_buf = emu.get_display();
+
+    let image = RgbaImage::from_fn(width as u32 * SCALE, height as u32 * SCALE, |x, y| {
+        let i = (y / SCALE) as usize * width + (x / SCALE) as usize;
+
+        if screen_buf[i] {
+            to_rgba(palette.fg)
+        } else {
+            to_rgba(palette.bg)
+        }
+    });
+
+    image.save(path)
+}
+
+fn to_rgba(color: Color) -> Rgba<u8> {
+    Rgba([color.r, color.g, color.b, color.a])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process};
+
+    use chip8_emu::asm::assemble;
+
+    use super::*;
+
+    // A PNG path in the temp directory, deleted when dropped
+    struct TempPng(PathBuf);
+
+    impl TempPng {
+        fn new(name: &str) -> Self {
+            Self(env::temp_dir().join(format!("chip8_emu-{name}-{}.png", process::id())))
+        }
+    }
+
+    impl Drop for TempPng {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    // The display after running a program, saved and read back
+    fn screenshot(name: &str, src: &str, ticks: usize) -> RgbaImage {
+        let mut emu = Emu::new_with_seed(0);
+        emu.load(&assemble(src).unwrap()).unwrap();
+
+        for _ in 0..ticks {
+            emu.tick().unwrap();
+        }
+
+        let png = TempPng::new(name);
+        save(&emu, Palette::theme("amber").unwrap(), &png.0).unwrap();
+
+        image::open(&png.0).unwrap().to_rgba8()
+    }
+
+    #[test]
+    fn saves_the_display_scaled_up() {
+        // The font's 0 in the top left corner
+        let image = screenshot("lores", "LD F, V0\nDRW V0, V0, 5", 2);
+        let palette = Palette::theme("amber").unwrap();
+
+        assert_eq!(image.dimensions(), (64 * SCALE, 32 * SCALE));
+        assert_eq!(*image.get_pixel(0, 0), to_rgba(palette.fg));
+        assert_eq!(*image.get_pixel(SCALE - 1, SCALE - 1), to_rgba(palette.fg));
+        assert_eq!(*image.get_pixel(SCALE, SCALE), to_rgba(palette.bg));
+    }
+
+    #[test]
+    fn saves_hires_displays() {
+        let image = screenshot("hires", "HIGH", 1);
+
+        assert_eq!(image.dimensions(), (128 * SCALE, 64 * SCALE));
+    }
+}
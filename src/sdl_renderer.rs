@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use chip8_emu::render::Renderer;
+use sdl2::{
+    pixels::Color,
+    rect::{Point, Rect},
+    render::{BlendMode, Canvas, Texture},
+    video::Window,
+};
+
+use crate::{overlay, palette::Palette};
+
+// Display pixels have to be at least this many window pixels across for the grid to be drawn
+const MIN_GRID_SCALE: f32 = 4.0;
+// How solid the grid lines are, out of 255
+const GRID_ALPHA: u8 = 0x50;
+
+// Draws to the window, scaled up as far as it fits and centered. Whole number scales are used
+// whenever the window is big enough, so every display pixel is the same size.
+//
+// The display is kept in a texture at its own resolution, and only the pixels that changed since
+// the last frame are drawn into it. The texture is then scaled up onto the window, with the
+// background color filling any space left around it.
+//
+// Pixels can also be made to fade out over a few frames after they're turned off, like the
+// phosphor of an old screen, which hides a lot of the flicker from games erasing and redrawing
+// sprites.
+pub struct SdlRenderer {
+    pub canvas: Canvas<Window>,
+    pub palette: Palette,
+    // Swaps the palette's colors
+    pub inverted: bool,
+
+    // Lines along the edges of the display pixels, to see where sprites line up
+    pub grid: bool,
+
+    // Text drawn over the top of the display, when set
+    pub overlay: Option<Vec<String>>,
+    // The sprite at I, boxed where it'd be drawn, when set
+    pub sprite: Option<overlay::Sprite>,
+
+    // Frames a pixel takes to fade out once it's turned off. 0 turns it straight off.
+    pub fade_frames: u32,
+    // How lit each pixel is, from 0 (bg) to 1 (fg)
+    brightness: Vec<f32>,
+
+    texture: Option<Texture>,
+    // What's in the texture, empty when it needs drawing from scratch
+    drawn: Vec<Color>,
+}
+
+impl SdlRenderer {
+    pub fn new(canvas: Canvas<Window>, palette: Palette) -> Self {
+        Self {
+            canvas,
+            palette,
+            inverted: false,
+            grid: false,
+            overlay: None,
+            sprite: None,
+            fade_frames: 0,
+            brightness: Vec::new(),
+            texture: None,
+            drawn: Vec::new(),
+        }
+    }
+
+    // The palette as it's being drawn with
+    pub fn colors(&self) -> Palette {
+        if self.inverted {
+            self.palette.inverted()
+        } else {
+            self.palette
+        }
+    }
+}
+
+impl Renderer for SdlRenderer {
+    fn draw(&mut self, screen: &[bool], width: usize, height: usize) {
+        let palette = self.colors();
+
+        // Start over when the resolution mode changes
+        let texture = match &mut self.texture {
+            Some(texture) if self.drawn.len() == screen.len() => texture,
+            _ => {
+                if let Some(old_texture) = self.texture.take() {
+                    // SAFETY: the old texture isn't referenced anywhere else, and the canvas
+                    // that created it is still alive
+                    unsafe { old_texture.destroy() };
+                }
+
+                let texture = self
+                    .canvas
+                    .texture_creator()
+                    .create_texture_target(None, width as u32, height as u32)
+                    .unwrap();
+                self.drawn.clear();
+                self.brightness = vec![0.0; screen.len()];
+
+                self.texture.insert(texture)
+            }
+        };
+
+        let fade_step = 1.0 / self.fade_frames.max(1) as f32;
+
+        let colors: Vec<_> = screen
+            .iter()
+            .zip(&mut self.brightness)
+            .map(|(&pixel, brightness)| {
+                *brightness = if pixel {
+                    1.0
+                } else {
+                    (*brightness - fade_step).max(0.0)
+                };
+
+                palette.blend(*brightness)
+            })
+            .collect();
+
+        // Changed pixels, grouped by their new color
+        let mut changed: HashMap<Color, Vec<Point>> = HashMap::new();
+
+        for (i, &color) in colors.iter().enumerate() {
+            if self.drawn.get(i) != Some(&color) {
+                let point = Point::new((i % width) as i32, (i / width) as i32);
+                changed.entry(color).or_default().push(point);
+            }
+        }
+
+        self.canvas
+            .with_texture_canvas(texture, |texture_canvas| {
+                for (color, points) in &changed {
+                    texture_canvas.set_draw_color(*color);
+                    texture_canvas.draw_points(&points[..]).unwrap();
+                }
+            })
+            .unwrap();
+
+        self.drawn = colors;
+
+        let (window_width, window_height) = self.canvas.output_size().unwrap();
+        let fit = (window_width as f32 / width as f32).min(window_height as f32 / height as f32);
+        let scale = if fit >= 1.0 { fit.floor() } else { fit };
+        let (display_width, display_height) = (
+            (width as f32 * scale) as u32,
+            (height as f32 * scale) as u32,
+        );
+
+        let display_rect = Rect::new(
+            ((window_width - display_width) / 2) as i32,
+            ((window_height - display_height) / 2) as i32,
+            display_width,
+            display_height,
+        );
+
+        self.canvas.set_draw_color(palette.bg);
+        self.canvas.clear();
+        self.canvas.copy(texture, None, display_rect).unwrap();
+
+        if self.grid {
+            draw_grid(&mut self.canvas, display_rect, width, height, palette.fg);
+        }
+
+        if let Some(sprite) = &self.sprite {
+            overlay::draw_sprite(&mut self.canvas, sprite, display_rect, width, palette.fg);
+        }
+
+        if let Some(lines) = &self.overlay {
+            overlay::draw(&mut self.canvas, lines, palette.fg);
+        }
+
+        self.canvas.present();
+    }
+}
+
+// Faint lines between the display pixels, left out when they're too small for it to show
+// anything but the lines
+fn draw_grid(
+    canvas: &mut Canvas<Window>,
+    display_rect: Rect,
+    width: usize,
+    height: usize,
+    color: Color,
+) {
+    let scale = display_rect.width() as f32 / width as f32;
+
+    if scale < MIN_GRID_SCALE {
+        return;
+    }
+
+    let (left, top) = (display_rect.left(), display_rect.top());
+    let (right, bottom) = (display_rect.right() - 1, display_rect.bottom() - 1);
+
+    let columns = (0..=width).map(|x| {
+        let x = (left + (x as f32 * scale) as i32).min(right);
+        (Point::new(x, top), Point::new(x, bottom))
+    });
+    let rows = (0..=height).map(|y| {
+        let y = (top + (y as f32 * scale) as i32).min(bottom);
+        (Point::new(left, y), Point::new(right, y))
+    });
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(color.r, color.g, color.b, GRID_ALPHA));
+
+    for (start, end) in columns.chain(rows) {
+        canvas.draw_line(start, end).unwrap();
+    }
+
+    canvas.set_blend_mode(BlendMode::None);
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chip8_emu::{constants::TIMER_FREQ, emu::Emu, render::Renderer};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, terminal,
+};
+use sdl2::keyboard::Keycode;
+
+use crate::keymap::Keymap;
+
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / TIMER_FREQ as u64);
+
+// Terminals only report key presses (and auto-repeats), not releases, so a key counts as held
+// until it hasn't been seen for this long
+const KEY_HOLD_TIME: Duration = Duration::from_millis(150);
+
+// Draws with half blocks, packing two rows of pixels into each line of text so the display
+// keeps roughly its proper shape
+pub struct TerminalRenderer<W: Write> {
+    out: W,
+}
+
+impl<W: Write> TerminalRenderer<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl<W: Write> Renderer for TerminalRenderer<W> {
+    fn draw(&mut self, screen: &[bool], width: usize, height: usize) {
+        // Back to the top left corner and draw over the last frame
+        let mut frame = String::from("\x1b[H");
+
+        for y in (0..height).step_by(2) {
+            for x in 0..width {
+                let top = screen[y * width + x];
+                let bottom = y + 1 < height && screen[(y + 1) * width + x];
+
+                frame.push(match (top, bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+
+            frame.push_str("\r\n");
+        }
+
+        // Nothing useful to do if the terminal has gone away
+        let _ = self.out.write_all(frame.as_bytes());
+        let _ = self.out.flush();
+    }
+}
+
+// Runs the emulator in the terminal until Escape or Ctrl+C. Keys are looked up in the keymap
+// by their SDL names, so the same config works for both frontends.
+pub fn run(emu: &mut Emu, keymap: &Keymap) -> ExitCode {
+    let mut stdout = io::stdout();
+
+    if let Err(err) = terminal::enable_raw_mode()
+        .and_then(|()| execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide))
+    {
+        eprintln!("Unable to set up the terminal: {err}");
+        return ExitCode::FAILURE;
+    }
+
+    let result = run_loop(emu, keymap, &mut TerminalRenderer::new(io::stdout()));
+
+    let _ = execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Emulation stopped: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_loop(
+    emu: &mut Emu,
+    keymap: &Keymap,
+    renderer: &mut impl Renderer,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // When each held button was last seen
+    let mut held: HashMap<usize, Instant> = HashMap::new();
+
+    loop {
+        let frame_start = Instant::now();
+
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind,
+                ..
+            }) = event::read()?
+            else {
+                continue;
+            };
+
+            match code {
+                KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                _ => (),
+            }
+
+            let Some(k) = button(keymap, code) else {
+                continue;
+            };
+
+            // Only terminals with the kitty keyboard protocol send releases
+            if kind == KeyEventKind::Release {
+                held.remove(&k);
+                emu.keypress(k, false);
+            } else {
+                held.insert(k, frame_start);
+                emu.keypress(k, true);
+            }
+        }
+
+        held.retain(|&k, last_seen| {
+            let still_held = frame_start - *last_seen < KEY_HOLD_TIME;
+
+            if !still_held {
+                emu.keypress(k, false);
+            }
+
+            still_held
+        });
+
+        emu.run_frame()?;
+        emu.render(renderer);
+
+        thread::sleep(FRAME_TIME.saturating_sub(frame_start.elapsed()));
+    }
+}
+
+fn button(keymap: &Keymap, code: KeyCode) -> Option<usize> {
+    let name = match code {
+        KeyCode::Char(c) => c.to_ascii_uppercase().to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        _ => return None,
+    };
+
+    keymap.button(Keycode::from_name(&name)?)
+}
@@ -0,0 +1,85 @@
+// Helpers for testing programs against the emulator, e.g. that a drawing routine puts the right
+// pixels on the screen:
+//
+//     // The font's 0, in the top left corner
+//     let emu = testing::run("LD F, V0\nDRW V0, V0, 5", 2);
+//     testing::assert_screen(
+//         &emu,
+//         "
+//         ####
+//         #..#
+//         #..#
+//         #..#
+//         ####
+//         ",
+//     );
+//
+// Runs are deterministic, with CXNN seeded the same way every time.
+
+use alloc::{string::String, vec::Vec};
+
+use crate::{asm::assemble, emu::Emu};
+
+// Assembles a program with `asm::assemble` and runs `ticks` instructions of it. Panics if it
+// doesn't assemble or the emulator stops with an error.
+pub fn run(src: &str, ticks: usize) -> Emu {
+    let rom = assemble(src).unwrap_or_else(|err| panic!("program doesn't assemble, {err}"));
+
+    run_rom(&rom, ticks)
+}
+
+// Like `run`, but with the program as bytes
+pub fn run_rom(rom: &[u8], ticks: usize) -> Emu {
+    let mut emu = Emu::new_with_seed(0);
+    emu.load(rom)
+        .unwrap_or_else(|err| panic!("program doesn't load, {err}"));
+
+    for tick in 0..ticks {
+        emu.tick()
+            .unwrap_or_else(|err| panic!("emulation stopped on tick {tick}, {err}"));
+    }
+
+    emu
+}
+
+// Panics unless the display matches `expected`, which is drawn as `Emu::display_text` draws it,
+// # for lit and . for unlit. Only the top left corner it covers needs drawing out, as the rest
+// of the display is expected to be unlit. Leading and trailing whitespace on each line, and blank
+// lines, are ignored, so it can be indented along with the code around it.
+pub fn assert_screen(emu: &Emu, expected: &str) {
+    let (width, height) = emu.display_size();
+
+    let expected_rows: Vec<_> = expected
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    assert!(
+        expected_rows.len() <= height
+            && expected_rows.iter().all(|row| row.chars().count() <= width),
+        "expected display is bigger than the {width}x{height} display"
+    );
+    assert!(
+        expected_rows
+            .iter()
+            .all(|row| row.chars().all(|c| c == '#' || c == '.')),
+        "expected display should only have # and ."
+    );
+
+    let mut expected = String::new();
+
+    for y in 0..height {
+        let row = expected_rows.get(y).copied().unwrap_or_default();
+
+        expected.extend((0..width).map(|x| row.chars().nth(x).unwrap_or('.')));
+        expected.push('\n');
+    }
+
+    let actual = emu.display_text();
+
+    assert!(
+        actual == expected,
+        "display doesn't match\nexpected:\n{expected}\nactual:\n{actual}"
+    );
+}
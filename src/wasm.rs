@@ -0,0 +1,72 @@
+// Bindings for running in a browser, see web/ for a page using them. The page owns the clock,
+// calling `run_frame` from requestAnimationFrame, and draws `frame_rgba` to a canvas.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{constants::NUM_KEYS, emu::Emu};
+
+#[wasm_bindgen]
+pub struct WebEmu {
+    emu: Emu,
+}
+
+#[wasm_bindgen]
+impl WebEmu {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WebEmu {
+        WebEmu { emu: Emu::new() }
+    }
+
+    // Takes a Uint8Array on the JS side
+    pub fn load(&mut self, rom: &[u8]) -> Result<(), JsError> {
+        self.emu.load(rom)?;
+
+        Ok(())
+    }
+
+    pub fn reset(&mut self) {
+        self.emu.reset();
+    }
+
+    pub fn tick(&mut self) -> Result<(), JsError> {
+        self.emu.tick()?;
+
+        Ok(())
+    }
+
+    pub fn run_frame(&mut self) -> Result<(), JsError> {
+        self.emu.run_frame()?;
+
+        Ok(())
+    }
+
+    // Ignores keys outside the keypad, so the page can pass along whatever it maps
+    pub fn keypress(&mut self, key: usize, pressed: bool) {
+        if key < NUM_KEYS {
+            self.emu.keypress(key, pressed);
+        }
+    }
+
+    pub fn is_beeping(&self) -> bool {
+        self.emu.is_beeping()
+    }
+
+    pub fn width(&self) -> usize {
+        self.emu.display_size().0
+    }
+
+    pub fn height(&self) -> usize {
+        self.emu.display_size().1
+    }
+
+    // RGBA bytes for `width` x `height` pixels, ready for an ImageData
+    pub fn frame_rgba(&self) -> Vec<u8> {
+        self.emu.get_frame_rgba()
+    }
+}
+
+impl Default for WebEmu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,45 @@
+// The bundled ROMs run headless for a fixed number of frames, with the display checked against
+// hashes of known good runs. A change to any instruction they use shows up as a different
+// display.
+//
+// When a change to the display is intended, print `emu.display_text()` to check the new one
+// is right before updating the hash.
+
+use chip8_emu::Emu;
+
+fn emu_running(rom: &[u8]) -> Emu {
+    let mut emu = Emu::new_with_seed(0);
+    emu.load(rom).unwrap();
+
+    emu
+}
+
+#[test]
+fn bounce() {
+    let mut emu = emu_running(include_bytes!("../roms/bounce.ch8"));
+
+    // The ball moves a pixel diagonally every 3 frames, bouncing off the bottom at row 30, so
+    // by now it's at (43, 17)
+    emu.run_frames(130).unwrap();
+
+    assert_eq!(emu.display_hash(), 0x2C90_7AF1_92B3_45E9);
+}
+
+#[test]
+fn keys() {
+    let mut emu = emu_running(include_bytes!("../roms/keys.ch8"));
+
+    // Nothing is drawn until a key has been pressed and released
+    emu.run_frames(120).unwrap();
+
+    assert_eq!(emu.display_hash(), 0x0B8B_5650_919E_108D);
+    assert!(emu.get_display().iter().all(|&pixel| !pixel));
+
+    emu.keypress(0xA, true);
+    emu.run_frames(5).unwrap();
+    emu.keypress(0xA, false);
+    emu.run_frames(5).unwrap();
+
+    // The font's A, in the middle of the display
+    assert_eq!(emu.display_hash(), 0x6B67_4336_4ECE_5C3B);
+}
@@ -0,0 +1,100 @@
+// Glue between the wasm bindings (built into pkg/, see the README) and the page. The browser
+// drives the clock with requestAnimationFrame, running however many 60Hz frames are due.
+
+import init, { WebEmu } from "./pkg/chip8_emu.js";
+
+const FRAME_TIME = 1000 / 60;
+
+// Frames owed after the tab was in the background are dropped past this point
+const MAX_FRAMES_BEHIND = 5;
+
+const DEMO = "../roms/bounce.ch8";
+
+// The left side of a QWERTY keyboard, by physical position so other layouts work too
+const KEYPAD = {
+  Digit1: 0x1, Digit2: 0x2, Digit3: 0x3, Digit4: 0xc,
+  KeyQ: 0x4, KeyW: 0x5, KeyE: 0x6, KeyR: 0xd,
+  KeyA: 0x7, KeyS: 0x8, KeyD: 0x9, KeyF: 0xe,
+  KeyZ: 0xa, KeyX: 0x0, KeyC: 0xb, KeyV: 0xf,
+};
+
+const canvas = document.getElementById("screen");
+const context = canvas.getContext("2d");
+
+await init();
+
+const emu = new WebEmu();
+emu.load(new Uint8Array(await (await fetch(DEMO)).arrayBuffer()));
+
+function keyHandler(pressed) {
+  return (event) => {
+    if (event.code in KEYPAD) {
+      emu.keypress(KEYPAD[event.code], pressed);
+      event.preventDefault();
+    }
+  };
+}
+
+document.addEventListener("keydown", keyHandler(true));
+document.addEventListener("keyup", keyHandler(false));
+
+document.getElementById("rom").addEventListener("change", async (event) => {
+  const file = event.target.files[0];
+
+  if (file) {
+    emu.load(new Uint8Array(await file.arrayBuffer()));
+    emu.reset();
+
+    if (!running) {
+      start();
+    }
+  }
+});
+
+function draw() {
+  const width = emu.width();
+  const height = emu.height();
+
+  // The size changes when a game switches between lo-res and hi-res
+  if (canvas.width !== width || canvas.height !== height) {
+    canvas.width = width;
+    canvas.height = height;
+  }
+
+  const pixels = new Uint8ClampedArray(emu.frame_rgba());
+  context.putImageData(new ImageData(pixels, width, height), 0, 0);
+}
+
+let running = false;
+let last;
+
+function start() {
+  running = true;
+  last = performance.now();
+  requestAnimationFrame(frame);
+}
+
+function frame(now) {
+  // The first timestamp can be from just before `start`
+  const owed = Math.max(Math.floor((now - last) / FRAME_TIME), 0);
+  const due = Math.min(owed, MAX_FRAMES_BEHIND);
+
+  try {
+    for (let i = 0; i < due; i++) {
+      emu.run_frame();
+    }
+  } catch (err) {
+    // Stays stopped until another game is picked
+    console.error(`Emulation stopped: ${err}`);
+    running = false;
+    return;
+  }
+
+  // Keep any leftover time, unless frames were dropped
+  last = due === MAX_FRAMES_BEHIND ? now : last + due * FRAME_TIME;
+
+  draw();
+  requestAnimationFrame(frame);
+}
+
+start();